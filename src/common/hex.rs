@@ -0,0 +1,91 @@
+use std::ops::Add;
+
+/// The six neighbours of a tile on a hex grid with pointy tops, so that two of the
+/// neighbours lie straight east and west.
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+pub enum HexDirection {
+    East,
+    SouthEast,
+    SouthWest,
+    West,
+    NorthWest,
+    NorthEast,
+}
+
+impl HexDirection {
+    pub const ALL:[HexDirection;6] = [
+        HexDirection::East,
+        HexDirection::SouthEast,
+        HexDirection::SouthWest,
+        HexDirection::West,
+        HexDirection::NorthWest,
+        HexDirection::NorthEast,
+    ];
+
+    pub const fn offset(&self) -> HexCoord {
+        match self {
+            HexDirection::East => HexCoord::new(1, 0),
+            HexDirection::SouthEast => HexCoord::new(0, 1),
+            HexDirection::SouthWest => HexCoord::new(-1, 1),
+            HexDirection::West => HexCoord::new(-1, 0),
+            HexDirection::NorthWest => HexCoord::new(0, -1),
+            HexDirection::NorthEast => HexCoord::new(1, -1),
+        }
+    }
+
+    /// Splits a run-together string of directions (`e`, `se`, `sw`, `w`, `nw`, `ne`), such as
+    /// `esenee`, into its steps.
+    pub fn parse_path(path:&str) -> Result<Vec<HexDirection>,String> {
+        let mut retval = Vec::new();
+        let mut chars = path.trim().chars();
+        while let Some(first) = chars.next() {
+            let direction = match first {
+                'e' => HexDirection::East,
+                'w' => HexDirection::West,
+                'n'|'s' => match (first,chars.next()) {
+                    ('n',Some('e')) => HexDirection::NorthEast,
+                    ('n',Some('w')) => HexDirection::NorthWest,
+                    ('s',Some('e')) => HexDirection::SouthEast,
+                    ('s',Some('w')) => HexDirection::SouthWest,
+                    (_,other) => return Err(format!("Expected e or w after {first}, found {other:?}.")),
+                },
+                other => return Err(format!("Unknown direction {other}.")),
+            };
+            retval.push(direction);
+        }
+        Ok(retval)
+    }
+}
+
+/// Axial coordinate of a tile on a hex grid; see `HexDirection` for the orientation.
+#[derive(Clone,Copy,PartialEq,Eq,Hash,Debug,Default)]
+pub struct HexCoord {
+    pub q:isize,
+    pub r:isize,
+}
+
+impl Add for HexCoord {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        HexCoord {
+            q: self.q + rhs.q,
+            r: self.r + rhs.r,
+        }
+    }
+}
+
+impl HexCoord {
+    pub const fn new(q:isize, r:isize) -> Self {
+        HexCoord { q, r }
+    }
+
+    pub fn neighbour(&self, direction:HexDirection) -> Self {
+        *self + direction.offset()
+    }
+
+    pub fn neighbours(&self) -> impl Iterator<Item = HexCoord> {
+        let origin = *self;
+        HexDirection::ALL.into_iter().map(move |direction| origin.neighbour(direction))
+    }
+}
@@ -0,0 +1,29 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+/// Advances a Game-of-Life-style automaton by one generation.
+/// `neighbours` yields the cells adjacent to a cell, and `rule` decides whether a cell is
+/// alive in the next generation, given whether it is alive now and its live neighbour count.
+pub fn step<T,N,I>(live:&HashSet<T>, neighbours:N, rule:fn(bool,usize) -> bool) -> HashSet<T>
+where
+    T:Copy + Eq + Hash,
+    N:Fn(&T) -> I,
+    I:Iterator<Item = T>,
+{
+    let mut counts:HashMap<T,usize> = HashMap::new();
+    for cell in live.iter() {
+        for adjacent in neighbours(cell) {
+            *counts.entry(adjacent).or_insert(0) += 1;
+        }
+    }
+
+    let mut retval:HashSet<T> = counts.iter()
+        .filter(|(cell,count)| rule(live.contains(*cell), **count))
+        .map(|(cell,_)| *cell)
+        .collect();
+    //Live cells without any live neighbours never made it into the counts.
+    if rule(true,0) {
+        retval.extend(live.iter().filter(|cell| !counts.contains_key(*cell)));
+    }
+    retval
+}
@@ -0,0 +1,6 @@
+pub mod hex;
+pub mod life;
+pub mod number_theory;
+pub mod parse;
+pub mod records;
+pub mod schema;
@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+
+fn mul_mod(a:u64, b:u64, modulus:u64) -> u64 {
+    ((a as u128 * b as u128) % modulus as u128) as u64
+}
+
+/// `base` to the power `exponent`, modulo `modulus`, by repeated squaring. Panics on a zero
+/// modulus.
+pub fn mod_pow(base:u64, exponent:u64, modulus:u64) -> u64 {
+    assert!(modulus > 0, "Cannot work modulo 0.");
+    if modulus == 1 {
+        return 0;
+    }
+    let mut retval = 1;
+    let mut base = base % modulus;
+    let mut exponent = exponent;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            retval = mul_mod(retval, base, modulus);
+        }
+        base = mul_mod(base, base, modulus);
+        exponent >>= 1;
+    }
+    retval
+}
+
+/// The `x` for which `value * x` is 1 modulo `modulus`, if `value` and `modulus` are coprime.
+/// Nothing has an inverse modulo 0.
+pub fn mod_inverse(value:u64, modulus:u64) -> Option<u64> {
+    if modulus == 0 {
+        return None;
+    }
+    //Extended Euclid, tracking only the coefficient for `value`.
+    let (mut old_r, mut r) = (value as i128 % modulus as i128, modulus as i128);
+    let (mut old_s, mut s) = (1i128, 0i128);
+    while r != 0 {
+        let quotient = old_r / r;
+        (old_r, r) = (r, old_r - quotient * r);
+        (old_s, s) = (s, old_s - quotient * s);
+    }
+    if old_r != 1 {
+        return None;
+    }
+    Some(old_s.rem_euclid(modulus as i128) as u64)
+}
+
+/// Smallest `x` for which `base` to the power `x` equals `target` modulo `modulus`, found with
+/// the baby-step giant-step algorithm in O(sqrt(modulus)) time and memory.
+/// `base` has to be coprime to `modulus`. Panics on a zero modulus.
+pub fn discrete_log(base:u64, target:u64, modulus:u64) -> Option<u64> {
+    assert!(modulus > 0, "Cannot work modulo 0.");
+    let target = target % modulus;
+    let steps = (modulus as f64).sqrt().ceil() as u64;
+
+    //Baby steps: base^j for every j below `steps`, keeping the smallest j per value.
+    let mut baby_steps:HashMap<u64,u64> = HashMap::with_capacity(steps as usize);
+    let mut current = 1 % modulus;
+    for j in 0..steps {
+        baby_steps.entry(current).or_insert(j);
+        current = mul_mod(current, base, modulus);
+    }
+
+    //Giant steps: target * base^(-steps*i), until it lands on a baby step.
+    let giant = mod_inverse(mod_pow(base, steps, modulus), modulus)?;
+    let mut current = target;
+    for i in 0..steps {
+        if let Some(j) = baby_steps.get(&current) {
+            return Some(i * steps + j);
+        }
+        current = mul_mod(current, giant, modulus);
+    }
+    None
+}
+
+/// Solves a system of congruences `x = residue (mod modulus)` with pairwise coprime moduli,
+/// using the Chinese Remainder Theorem. Returns the smallest non-negative solution and the
+/// product of the moduli. Returns `None` if two moduli share a factor, if a modulus is 0, or if
+/// the product of the moduli does not fit in a `u64`.
+pub fn crt(congruences:&[(u64,u64)]) -> Option<(u64,u64)> {
+    if congruences.iter().any(|(_,modulus)| *modulus == 0) {
+        return None;
+    }
+    let product = congruences.iter().try_fold(1u64, |acc, (_,modulus)| acc.checked_mul(*modulus))?;
+    let mut retval = 0;
+    for (residue, modulus) in congruences.iter() {
+        let rest = product / modulus;
+        let inverse = mod_inverse(rest % modulus, *modulus)?;
+        let term = mul_mod(mul_mod(residue % modulus, inverse, product), rest, product);
+        retval = ((u128::from(retval) + u128::from(term)) % u128::from(product)) as u64;
+    }
+    Some((retval, product))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn powers_and_inverses() {
+        assert_eq!(mod_pow(7, 8, 20201227), 5764801);
+        assert_eq!(mod_pow(2, 10, 1000), 24);
+        assert_eq!(mod_pow(5, 0, 13), 1);
+        assert_eq!(mod_pow(5, 3, 1), 0);
+        assert_eq!(mod_inverse(3, 11), Some(4));
+        assert_eq!(mod_inverse(10, 17).map(|inverse| inverse * 10 % 17), Some(1));
+        assert_eq!(mod_inverse(2, 4), None);
+        assert_eq!(mod_inverse(3, 0), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot work modulo 0.")]
+    fn mod_pow_rejects_a_zero_modulus() {
+        mod_pow(3, 4, 0);
+    }
+
+    #[test]
+    fn discrete_log_finds_the_puzzle_loop_sizes() {
+        assert_eq!(discrete_log(7, 5764801, 20201227), Some(8));
+        assert_eq!(discrete_log(7, 17807724, 20201227), Some(11));
+    }
+
+    #[test]
+    fn discrete_log_matches_brute_force() {
+        for modulus in [2u64, 7, 11, 23, 97, 101, 1009] {
+            for base in 1..modulus.min(30) {
+                //Smallest exponent reaching each value, by walking through the powers.
+                let mut smallest:HashMap<u64,u64> = HashMap::new();
+                let mut current = 1 % modulus;
+                for exponent in 0..modulus {
+                    smallest.entry(current).or_insert(exponent);
+                    current = current * base % modulus;
+                }
+                for target in 0..modulus {
+                    assert_eq!(discrete_log(base, target, modulus), smallest.get(&target).copied(),
+                        "base {base}, target {target}, modulus {modulus}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn crt_solves_the_bus_schedule() {
+        //Day 13's example: bus `id` at offset `offset` leaves `offset` minutes after the answer.
+        let buses = [(0u64,7u64), (1,13), (4,59), (6,31), (7,19)];
+        let congruences:Vec<(u64,u64)> = buses.iter().map(|(offset,id)| ((id - offset % id) % id, *id)).collect();
+        assert_eq!(crt(&congruences), Some((1068781, 7 * 13 * 59 * 31 * 19)));
+    }
+
+    #[test]
+    fn crt_edge_cases() {
+        assert_eq!(crt(&[]), Some((0, 1)));
+        assert_eq!(crt(&[(5, 3)]), Some((2, 3)));
+        assert_eq!(crt(&[(1, 4), (1, 6)]), None);
+        assert_eq!(crt(&[(1, u64::MAX), (0, 2)]), None);
+        assert_eq!(crt(&[(0, 0), (1, 3)]), None);
+    }
+
+    #[test]
+    fn crt_with_moduli_near_the_limit() {
+        //Two primes whose product is just below 2^64.
+        let (p, q) = (4294967291u64, 4294967279u64);
+        for (a, b) in [(p - 1, q - 1), (p - 1, q - 2), (p / 2, q - 1), (1, q - 1), (p - 1, 0)] {
+            let congruences = [(a, p), (b, q)];
+            let (solution, product) = crt(&congruences).expect("The moduli are coprime.");
+            assert_eq!(product, p * q);
+            for (residue, modulus) in congruences {
+                assert_eq!(solution % modulus, residue);
+            }
+        }
+    }
+}
@@ -0,0 +1,14 @@
+use std::fmt;
+
+/// Why a line of puzzle input could not be read, and where on the line it went wrong.
+pub struct ParseError {
+    /// 1-based, counted in characters.
+    pub column:usize,
+    pub message:String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f:&mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f,"column {}: {}",self.column,self.message)
+    }
+}
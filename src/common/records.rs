@@ -0,0 +1,33 @@
+/// A group of consecutive non-blank lines, such as a passport or the answers of a group.
+pub struct Record<'a> {
+    /// 1-based line number of the first line in the input.
+    pub first_line:usize,
+    pub lines:Vec<&'a str>,
+}
+
+impl<'a> Record<'a> {
+    /// The lines of the record, with their 1-based line numbers in the input.
+    pub fn numbered_lines(&self) -> impl Iterator<Item = (usize,&'a str)> + '_ {
+        self.lines.iter().enumerate().map(|(offset,line)| (self.first_line + offset, *line))
+    }
+}
+
+/// Splits the input into records separated by blank lines. Runs of blank lines count as a
+/// single separator, lines holding only whitespace count as blank, and the last record is kept
+/// whether or not the input ends with a blank line. Both `\n` and `\r\n` line endings work.
+pub fn read_records(input:&str) -> Vec<Record<'_>> {
+    let mut retval:Vec<Record> = Vec::new();
+    let mut current:Option<Record> = None;
+    for (index,line) in input.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() {
+            retval.extend(current.take());
+            continue;
+        }
+        current.get_or_insert_with(|| Record { first_line: index+1, lines: Vec::new() })
+            .lines
+            .push(line);
+    }
+    retval.extend(current);
+    retval
+}
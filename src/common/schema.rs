@@ -0,0 +1,267 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs;
+
+use regex::Regex;
+
+/// The ways in which a field can be rejected.
+#[derive(Clone,Copy,PartialEq,Eq,PartialOrd,Ord,Hash,Debug)]
+pub enum FailureKind {
+    Missing,
+    OutOfRange,
+    WrongUnit,
+    BadFormat,
+}
+
+impl Display for FailureKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}",
+        match self {
+            FailureKind::Missing => "missing",
+            FailureKind::OutOfRange => "out of range",
+            FailureKind::WrongUnit => "wrong unit",
+            FailureKind::BadFormat => "bad format",
+        })
+    }
+}
+
+/// A field of a record that was rejected by its rule.
+pub struct FieldFailure {
+    pub field:String,
+    pub kind:FailureKind,
+    /// The raw value; `None` if the field is missing.
+    pub value:Option<String>,
+    pub reason:String,
+    /// The custom message of the rule, if it has one.
+    pub message:Option<String>,
+}
+
+impl Display for FieldFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.value {
+            Some(value) => write!(f,"{} <{value}>, {}: ",self.field,self.kind)?,
+            None => write!(f,"{}, {}: ",self.field,self.kind)?,
+        }
+        write!(f,"{}",self.message.as_ref().unwrap_or(&self.reason))
+    }
+}
+
+/// How the value of a field is checked.
+pub enum FieldType {
+    /// Whole number within an inclusive range, optionally with an exact number of digits.
+    IntRange { min:i64, max:i64, digits:Option<usize> },
+    /// Whole number directly followed by a unit, with an inclusive range per unit.
+    UnitRange(Vec<(String,i64,i64)>),
+    /// The whole value matches the pattern.
+    Pattern(Regex),
+    /// One of a fixed set of values.
+    OneOf(Vec<String>),
+    /// `#` followed by this many lowercase hexadecimal digits.
+    HexColour { digits:usize },
+    /// Anything goes, as long as the field is there.
+    Any,
+}
+
+impl FieldType {
+    /// Why the value does not fit this type, if it doesn't.
+    pub fn check(&self, value:&str) -> Result<(),(FailureKind,String)> {
+        let bad_format = |reason:String| Err((FailureKind::BadFormat,reason));
+        match self {
+            FieldType::IntRange { min, max, digits } => {
+                if let Some(digits) = digits {
+                    if value.len() != *digits {
+                        return bad_format(format!("expected {digits} digits, found {}",value.len()));
+                    }
+                }
+                let Ok(number) = value.parse::<i64>() else {
+                    return bad_format("not a whole number".into());
+                };
+                if number < *min || number > *max {
+                    return Err((FailureKind::OutOfRange,format!("{number} is not within {min} to {max}")));
+                }
+                Ok(())
+            },
+            FieldType::UnitRange(units) => {
+                let split = value.find(|c:char| !c.is_ascii_digit()).unwrap_or(value.len());
+                let (number, unit) = value.split_at(split);
+                let Ok(number) = number.parse::<i64>() else {
+                    return bad_format("does not start with a whole number".into());
+                };
+                let Some((_, min, max)) = units.iter().find(|(name,_,_)| name == unit) else {
+                    let known:Vec<&str> = units.iter().map(|(name,_,_)| name.as_str()).collect();
+                    return Err((FailureKind::WrongUnit,format!("unit <{unit}> is not one of {}",known.join(", "))));
+                };
+                if number < *min || number > *max {
+                    return Err((FailureKind::OutOfRange,format!("{number}{unit} is not within {min} to {max}{unit}")));
+                }
+                Ok(())
+            },
+            FieldType::Pattern(regex) => {
+                if regex.is_match(value) {
+                    Ok(())
+                } else {
+                    bad_format(format!("does not match {}",regex.as_str()))
+                }
+            },
+            FieldType::OneOf(options) => {
+                if options.iter().any(|option| option == value) {
+                    Ok(())
+                } else {
+                    bad_format(format!("not one of {}",options.join(", ")))
+                }
+            },
+            FieldType::HexColour { digits } => {
+                let valid = value.strip_prefix('#')
+                    .is_some_and(|hex| hex.len() == *digits && hex.chars().all(|c| matches!(c, '0'..='9'|'a'..='f')));
+                if valid {
+                    Ok(())
+                } else {
+                    bad_format(format!("not # followed by {digits} hexadecimal digits"))
+                }
+            },
+            FieldType::Any => Ok(()),
+        }
+    }
+}
+
+pub struct FieldRule {
+    pub name:String,
+    pub required:bool,
+    pub kind:FieldType,
+    /// Shown instead of the generated reason when the field is rejected.
+    pub message:Option<String>,
+}
+
+/// A set of field rules that a record, a map of field names to values, has to satisfy.
+/// Fields without a rule are ignored.
+///
+/// Schemas are written one field per line, as
+/// `<name> required|optional <type> [arguments...] [-- <message>]`, where the type is one of
+/// - `int <min>..<max> [digits=<n>]`
+/// - `unit <unit>=<min>..<max>...`
+/// - `regex <pattern>`, matched against the whole value; the pattern cannot hold spaces
+/// - `enum <value>...`
+/// - `hex <digits>`
+/// - `any`
+///
+/// Empty lines and lines starting with `#` are skipped.
+pub struct Schema {
+    pub fields:Vec<FieldRule>,
+}
+
+fn parse_range(range:&str) -> Result<(i64,i64),String> {
+    let (min, max) = range.split_once("..").ok_or(format!("expected a range as <min>..<max>, found <{range}>"))?;
+    let min = min.parse::<i64>().map_err(|_| format!("could not parse <{min}>"))?;
+    let max = max.parse::<i64>().map_err(|_| format!("could not parse <{max}>"))?;
+    Ok((min,max))
+}
+
+fn parse_field_type(kind:&str, arguments:&[&str]) -> Result<FieldType,String> {
+    match (kind, arguments) {
+        ("int", [range, rest @ ..]) => {
+            let (min, max) = parse_range(range)?;
+            let digits = match rest {
+                [] => None,
+                [digits] => {
+                    let count = digits.strip_prefix("digits=").ok_or(format!("unexpected <{digits}>"))?;
+                    Some(count.parse::<usize>().map_err(|_| format!("could not parse <{count}>"))?)
+                },
+                _ => return Err("int takes a range and an optional digits=<n>".into()),
+            };
+            Ok(FieldType::IntRange { min, max, digits })
+        },
+        ("unit", units) if !units.is_empty() => {
+            let units = units.iter()
+                .map(|unit| {
+                    let (name, range) = unit.split_once('=').ok_or(format!("expected <unit>=<min>..<max>, found <{unit}>"))?;
+                    let (min, max) = parse_range(range)?;
+                    Ok((name.to_string(),min,max))
+                })
+                .collect::<Result<Vec<_>,String>>()?;
+            Ok(FieldType::UnitRange(units))
+        },
+        ("regex", [pattern]) => {
+            //Anchor the pattern, so that it has to match the whole value.
+            Regex::new(&format!("^(?:{pattern})$"))
+                .map(FieldType::Pattern)
+                .map_err(|e| format!("invalid regex <{pattern}>: {e}"))
+        },
+        ("enum", options) if !options.is_empty() => Ok(FieldType::OneOf(options.iter().map(|o| o.to_string()).collect())),
+        ("hex", [digits]) => {
+            let digits = digits.parse::<usize>().map_err(|_| format!("could not parse <{digits}>"))?;
+            Ok(FieldType::HexColour { digits })
+        },
+        ("any", []) => Ok(FieldType::Any),
+        _ => Err(format!("unknown type <{kind}>, or wrong arguments for it")),
+    }
+}
+
+fn parse_rule(line:&str) -> Result<FieldRule,String> {
+    let (rule, message) = match line.split_once(" -- ") {
+        Some((rule, message)) => (rule, Some(message.trim().to_string())),
+        None => (line, None),
+    };
+    let tokens:Vec<&str> = rule.split_whitespace().collect();
+    let [name, presence, kind, arguments @ ..] = &tokens[..] else {
+        return Err("expected <name> required|optional <type> [arguments...]".into());
+    };
+    let required = match *presence {
+        "required" => true,
+        "optional" => false,
+        other => return Err(format!("expected required or optional, found <{other}>")),
+    };
+    let kind = parse_field_type(kind, arguments)?;
+    Ok(FieldRule { name: name.to_string(), required, kind, message })
+}
+
+impl Schema {
+    pub fn parse(text:&str) -> Result<Self,String> {
+        let mut fields = Vec::new();
+        for (index,line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let rule = parse_rule(line).map_err(|e| format!("Schema line {}: {e}",index+1))?;
+            fields.push(rule);
+        }
+        Ok(Schema { fields })
+    }
+
+    pub fn load(path:&str) -> Result<Self,String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("Could not read schema {path}: {e}"))?;
+        Self::parse(&text)
+    }
+
+    /// Whether every required field is present, regardless of its value.
+    pub fn has_required_fields(&self, record:&HashMap<String,String>) -> bool {
+        self.fields.iter().all(|rule| !rule.required || record.contains_key(&rule.name))
+    }
+
+    /// Checks the record against every rule, in order, and returns every field that is rejected.
+    pub fn diagnose(&self, record:&HashMap<String,String>) -> Vec<FieldFailure> {
+        let mut retval = Vec::new();
+        for rule in self.fields.iter() {
+            let value = record.get(&rule.name);
+            let result = match value {
+                Some(value) => rule.kind.check(value),
+                None if rule.required => Err((FailureKind::Missing,"required field is missing".into())),
+                None => Ok(()),
+            };
+            if let Err((kind, reason)) = result {
+                retval.push(FieldFailure {
+                    field: rule.name.clone(),
+                    kind,
+                    value: value.cloned(),
+                    reason,
+                    message: rule.message.clone(),
+                });
+            }
+        }
+        retval
+    }
+
+    pub fn is_valid(&self, record:&HashMap<String,String>) -> bool {
+        self.diagnose(record).is_empty()
+    }
+}
@@ -1,4 +1,6 @@
-use std::{str::FromStr, fs, collections::HashSet};
+use std::{str::FromStr, fs};
+use std::collections::BTreeSet;
+use std::io::{self, BufRead, Write};
 
 #[derive(Copy,Clone)]
 enum Instruction {
@@ -11,12 +13,13 @@ impl FromStr for Instruction {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let opcode = &s[..3];
-        let mut value:i32 = s[5..].parse().unwrap();
-        let sign = &s[4..5] == "+";
-        if !sign {
-            value *= -1;
+        let Some((opcode,value)) = s.split_once(' ') else {
+            return Err(format!("<{s}> is not an opcode and an argument"));
         };
+        if !value.starts_with(['+','-']) {
+            return Err(format!("Argument <{value}> should start with + or -"));
+        }
+        let value:i32 = value.parse().map_err(|_| format!("<{value}> is not a number"))?;
         match opcode {
             "nop" => Ok(Instruction::Nop(value)),
             "acc" => Ok(Instruction::Acc(value)),
@@ -36,6 +39,87 @@ impl ToString for Instruction {
     }
 }
 
+impl Instruction {
+    /// The instruction with `jmp` and `nop` swapped, or nothing for instructions that can't be.
+    fn flipped(&self) -> Option<Instruction> {
+        match self {
+            Instruction::Nop(x) => Some(Instruction::Jmp(*x)),
+            Instruction::Jmp(x) => Some(Instruction::Nop(*x)),
+            Instruction::Acc(_) => None,
+        }
+    }
+}
+
+/// Where the machine ended up after a step.
+#[derive(Clone,Copy,PartialEq,Debug)]
+enum StepOutcome {
+    /// The next instruction has not run yet.
+    Ran,
+    /// The next instruction has run before, so the program will go round forever.
+    Looped,
+    /// The pc is just past the last instruction.
+    Terminated,
+    /// The pc is somewhere other than an instruction or just past the last one.
+    OutOfBounds(isize),
+}
+
+/// The handheld console: a program, and the registers running it.
+struct Machine {
+    program:Vec<Instruction>,
+    /// Signed, so a jump to before the first instruction can be reported.
+    pc:isize,
+    acc:i64,
+    /// Which instructions have run at least once.
+    visited:Vec<bool>,
+}
+
+impl Machine {
+    fn new(program:Vec<Instruction>) -> Self {
+        let visited = vec![false;program.len()];
+        Machine { program, pc: 0, acc: 0, visited }
+    }
+
+    /// Whether the machine can no longer run, and why.
+    fn halted(&self) -> Option<StepOutcome> {
+        let length = self.program.len() as isize;
+        if self.pc == length {
+            Some(StepOutcome::Terminated)
+        } else if self.pc < 0 || self.pc > length {
+            Some(StepOutcome::OutOfBounds(self.pc))
+        } else if self.visited[self.pc as usize] {
+            Some(StepOutcome::Looped)
+        } else {
+            None
+        }
+    }
+
+    /// Runs the instruction at the pc, unless the machine has halted; either way, tells where
+    /// that leaves the machine.
+    fn step(&mut self) -> StepOutcome {
+        if let Some(outcome) = self.halted() {
+            return outcome;
+        }
+        let pc = self.pc as usize;
+        self.visited[pc] = true;
+        match self.program[pc] {
+            Instruction::Nop(_) => {self.pc += 1;},
+            Instruction::Acc(x) => {self.acc += i64::from(x); self.pc += 1;},
+            Instruction::Jmp(x) => {self.pc += x as isize;},
+        }
+        self.halted().unwrap_or(StepOutcome::Ran)
+    }
+
+    /// Steps until the machine halts.
+    fn run(&mut self) -> StepOutcome {
+        loop {
+            let outcome = self.step();
+            if outcome != StepOutcome::Ran {
+                return outcome;
+            }
+        }
+    }
+}
+
 fn setup(input_path:&str) -> Vec<Instruction> {
     let input_string = fs::read_to_string(input_path).expect("Could not read file.");
     let mut retval:Vec<Instruction> = Vec::new();
@@ -47,65 +131,310 @@ fn setup(input_path:&str) -> Vec<Instruction> {
     return retval;
 }
 
-fn star_one(program:&Vec<Instruction>) -> String {
-    let mut visited:HashSet<usize> = HashSet::new();
-    let mut acc:i32 = 0;
-    let mut pc:usize = 0;
-    loop {
-        let current = &program[pc];
-        visited.insert(pc);
-        match current {
-            Instruction::Nop(_) => {pc += 1;},
-            Instruction::Acc(x) => {acc += x; pc += 1;},
-            Instruction::Jmp(x) => {pc = (*x + pc as i32) as usize ;},
+/// Where control goes after the instruction at `pc`.
+fn successor(pc:usize, instruction:Instruction) -> isize {
+    match instruction {
+        Instruction::Jmp(x) => pc as isize + x as isize,
+        Instruction::Nop(_) | Instruction::Acc(_) => pc as isize + 1,
+    }
+}
+
+/// For every instruction, and for the spot just past the end, whether running from there ends
+/// in termination. Walks the control flow backwards from the end, so each instruction is looked
+/// at once.
+fn reaches_end(program:&[Instruction]) -> Vec<bool> {
+    let length = program.len();
+    let mut predecessors:Vec<Vec<usize>> = vec![Vec::new();length+1];
+    for (pc,instruction) in program.iter().enumerate() {
+        let next = successor(pc, *instruction);
+        if (0..=length as isize).contains(&next) {
+            predecessors[next as usize].push(pc);
         }
-        if visited.contains(&pc) {
-            break;
+    }
+    let mut retval = vec![false;length+1];
+    retval[length] = true;
+    let mut to_check = vec![length];
+    while let Some(pc) = to_check.pop() {
+        for previous in predecessors[pc].iter() {
+            if !retval[*previous] {
+                retval[*previous] = true;
+                to_check.push(*previous);
+            }
         }
     }
-    acc.to_string()
+    retval
 }
 
-fn star_two(program:&Vec<Instruction>) -> String {
-    let mut visited:HashSet<usize> = HashSet::new();
-    let mut acc:i32;
-    let mut pc:usize;
-    let mut changed:usize = 0;
+/// The one instruction changed to make a program terminate, and the accumulator it ends with.
+struct Repair {
+    index:usize,
+    original:Instruction,
+    replacement:Instruction,
+    acc:i64,
+}
+
+enum RepairOutcome {
+    /// The program terminates as it is, with this accumulator.
+    AlreadyTerminates(i64),
+    Repaired(Repair),
+    /// No single swapped `jmp` or `nop` makes the program terminate.
+    Unrepairable,
+}
 
-    'outer: loop {
-        if let Instruction::Acc(_) = program[changed] {
-            changed += 1;
-            continue;
+/// Finds the `jmp` or `nop` to swap so the program terminates. Only instructions the unchanged
+/// program runs can matter, so it follows that run once, and swaps the first one whose swapped
+/// successor is known to reach the end.
+fn repair(program:&[Instruction]) -> RepairOutcome {
+    let mut machine = Machine::new(program.to_vec());
+    if machine.run() == StepOutcome::Terminated {
+        return RepairOutcome::AlreadyTerminates(machine.acc);
+    }
+
+    let reaches = reaches_end(program);
+    let mut machine = Machine::new(program.to_vec());
+    while machine.halted().is_none() {
+        let index = machine.pc as usize;
+        let original = program[index];
+        if let Some(replacement) = original.flipped() {
+            let next = successor(index, replacement);
+            if (0..=program.len() as isize).contains(&next) && reaches[next as usize] {
+                let mut patched = program.to_vec();
+                patched[index] = replacement;
+                let mut fixed = Machine::new(patched);
+                //From `next`, the unchanged program reaches the end without passing `index`;
+                //if it did pass it, the unchanged program would have terminated already.
+                let outcome = fixed.run();
+                debug_assert_eq!(outcome, StepOutcome::Terminated, "Swapping instruction {index} should make the program terminate.");
+                return RepairOutcome::Repaired(Repair { index, original, replacement, acc: fixed.acc });
+            }
         }
+        machine.step();
+    }
+    RepairOutcome::Unrepairable
+}
+
+/// Reads a program for one of the commands, failing on the first line that is not an instruction,
+/// so instruction `n` (counted from 0, like the pc) is always on line `n+1`. Blank lines at the
+/// end are left out; anywhere else they would shift the numbering, so they count as bad lines.
+fn load_program(input_path:&str) -> Result<Vec<Instruction>,String> {
+    let input_string = fs::read_to_string(input_path).map_err(|e| format!("Could not read {input_path}: {e}"))?;
+    let mut lines:Vec<&str> = input_string.lines().map(|line| line.trim_end_matches('\r')).collect();
+    while lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.pop();
+    }
+    lines.into_iter()
+        .enumerate()
+        .map(|(index,line)| if line.trim().is_empty() {
+            Err(format!("Line {} (instruction {index}): blank line inside the program",index+1))
+        } else {
+            Instruction::from_str(line).map_err(|e| format!("Line {} (instruction {index}): {e}",index+1))
+        })
+        .collect()
+}
+
+/// A machine under the debugger's control.
+struct Debugger {
+    machine:Machine,
+    /// Instructions to stop in front of when continuing.
+    breakpoints:BTreeSet<usize>,
+    /// Stop when continuing changes the accumulator.
+    watch_acc:bool,
+    /// Print every step when continuing, not just where it stops.
+    trace:bool,
+}
+
+const DEBUGGER_HELP:&str = "Instructions are numbered from 0, like the pc; instruction <pc> is on line <pc>+1.
+Commands:
+    step [n]          run one (or n) instructions, printing each
+    continue          run until a breakpoint, a watch, a loop or the end
+    break <pc>        stop in front of instruction <pc> when continuing
+    delete <pc>       remove the breakpoint on <pc>
+    breaks            list breakpoints
+    watch             toggle stopping when acc changes
+    trace             toggle printing every step when continuing
+    regs              print pc, acc and the next instruction
+    reset             start the program over; breakpoints are kept
+    quit";
 
-        visited.clear();
-        acc = 0;
-        pc = 0;
+impl Debugger {
+    fn new(program:Vec<Instruction>) -> Self {
+        Debugger { machine: Machine::new(program), breakpoints: BTreeSet::new(), watch_acc: false, trace: false }
+    }
+
+    /// The next instruction and the registers, as `pc 4 (line 5): JMP (-3)  acc 1`.
+    fn registers(&self) -> String {
+        let machine = &self.machine;
+        match usize::try_from(machine.pc).ok().and_then(|pc| machine.program.get(pc)) {
+            Some(instruction) => format!("pc {} (line {}): {}  acc {}",machine.pc,machine.pc+1,instruction.to_string(),machine.acc),
+            None => format!("pc {}: (none)  acc {}",machine.pc,machine.acc),
+        }
+    }
+
+    /// Runs one instruction and returns a trace line for it, or nothing if the machine has halted.
+    fn step(&mut self) -> Option<String> {
+        if self.machine.halted().is_some() {
+            return None;
+        }
+        let before = self.registers();
+        self.machine.step();
+        Some(format!("{before}  ->  pc {}  acc {}",self.machine.pc,self.machine.acc))
+    }
+
+    /// Why the machine has halted, if it has.
+    fn halt_reason(&self) -> Option<String> {
+        match self.machine.halted()? {
+            StepOutcome::Ran => None,
+            StepOutcome::Looped => Some(format!("Stopped: instruction {} has run before, the program loops.",self.machine.pc)),
+            StepOutcome::Terminated => Some("Stopped: the program terminated.".to_string()),
+            StepOutcome::OutOfBounds(pc) => Some(format!("Stopped: jumped to {pc}, outside the program.")),
+        }
+    }
+
+    fn continue_run(&mut self) {
+        let mut first = true;
         loop {
-            if visited.contains(&pc) {
+            if let Some(reason) = self.halt_reason() {
+                println!("{reason}");
                 break;
             }
-            if pc >= program.len() {
-                break 'outer;
+            let at_breakpoint = usize::try_from(self.machine.pc).is_ok_and(|pc| self.breakpoints.contains(&pc));
+            if at_breakpoint && !first {
+                println!("Breakpoint at {} (line {}).",self.machine.pc,self.machine.pc+1);
+                break;
             }
-            visited.insert(pc);
-            let mut instruction = program[pc];
-            if pc == changed {
-                match instruction {
-                    Instruction::Acc(_) => {panic!("SHOULD NEVER HAPPEN!!!");},
-                    Instruction::Jmp(x) => {instruction = Instruction::Nop(x);},
-                    Instruction::Nop(x) => {instruction = Instruction::Jmp(x);},
+            first = false;
+            let acc = self.machine.acc;
+            if let Some(line) = self.step() {
+                if self.trace {
+                    println!("{line}");
                 }
             }
-            match instruction {
-                Instruction::Nop(_) => {pc += 1;},
-                Instruction::Acc(x) => {acc += x;pc += 1;},
-                Instruction::Jmp(x) => {pc = (x + pc as i32) as usize}
+            if self.watch_acc && acc != self.machine.acc {
+                println!("Watch: acc {acc} -> {}.",self.machine.acc);
+                break;
             }
         }
-        changed += 1;
+        println!("{}",self.registers());
+    }
+
+    /// Carries out one command; returns false once the user wants to quit.
+    fn execute(&mut self, command:&str) -> Result<bool,String> {
+        let words:Vec<&str> = command.split_whitespace().collect();
+        let parse_pc = |word:&str| word.parse::<usize>().map_err(|_| format!("<{word}> is not an instruction number."));
+        match words[..] {
+            [] => {},
+            ["step"] | ["s"] => self.execute("step 1").map(|_| ())?,
+            ["step",count] | ["s",count] => {
+                let count:usize = count.parse().map_err(|_| format!("<{count}> is not a number of steps."))?;
+                for _ in 0..count {
+                    match self.step() {
+                        Some(line) => println!("{line}"),
+                        None => break,
+                    }
+                }
+                if let Some(reason) = self.halt_reason() {
+                    println!("{reason}");
+                }
+            },
+            ["continue"] | ["c"] => self.continue_run(),
+            ["break",pc] | ["b",pc] => {
+                let pc = parse_pc(pc)?;
+                match self.machine.program.len() {
+                    0 => return Err("The program has no instructions.".into()),
+                    length if pc >= length => return Err(format!("The program has no instruction {pc}; they are numbered 0 to {}.",length-1)),
+                    _ => {},
+                }
+                self.breakpoints.insert(pc);
+            },
+            ["delete",pc] | ["d",pc] => {
+                if !self.breakpoints.remove(&parse_pc(pc)?) {
+                    return Err(format!("No breakpoint on {pc}."));
+                }
+            },
+            ["breaks"] => {
+                for pc in self.breakpoints.iter() {
+                    println!("  {pc} (line {}): {}",pc+1,self.machine.program[*pc].to_string());
+                }
+            },
+            ["watch"] | ["w"] => {
+                self.watch_acc = !self.watch_acc;
+                println!("Watching acc: {}",if self.watch_acc { "on" } else { "off" });
+            },
+            ["trace"] | ["t"] => {
+                self.trace = !self.trace;
+                println!("Trace: {}",if self.trace { "on" } else { "off" });
+            },
+            ["regs"] | ["r"] => println!("{}",self.registers()),
+            ["reset"] => {
+                self.machine = Machine::new(std::mem::take(&mut self.machine.program));
+                println!("{}",self.registers());
+            },
+            ["help"] | ["h"] => println!("{DEBUGGER_HELP}"),
+            ["quit"] | ["q"] => return Ok(false),
+            _ => return Err(format!("Unknown command <{command}>. Try <help>.")),
+        }
+        Ok(true)
     }
-    acc.to_string()
+}
+
+/// `day8-debug <input>`: steps through a program interactively, reading commands from stdin.
+pub fn debug_command(args:&[String]) -> Result<(),String> {
+    let [input_path] = args else {
+        return Err("Usage: day8-debug <input>".into());
+    };
+    let mut debugger = Debugger::new(load_program(input_path)?);
+    println!("{} instructions loaded. Type <help> for commands.",debugger.machine.program.len());
+    println!("{}",debugger.registers());
+    let stdin = io::stdin();
+    loop {
+        print!("(day8) ");
+        io::stdout().flush().map_err(|e| format!("Could not write prompt: {e}"))?;
+        let mut command = String::new();
+        if stdin.lock().read_line(&mut command).map_err(|e| format!("Could not read command: {e}"))? == 0 {
+            println!();
+            return Ok(());
+        }
+        match debugger.execute(command.trim()) {
+            Ok(true) => {},
+            Ok(false) => return Ok(()),
+            Err(message) => println!("{message}"),
+        }
+    }
+}
+
+fn star_one(program:&Vec<Instruction>) -> String {
+    let mut machine = Machine::new(program.clone());
+    machine.run();
+    machine.acc.to_string()
+}
+
+fn star_two(program:&Vec<Instruction>) -> String {
+    match repair(program) {
+        RepairOutcome::Repaired(repair) => repair.acc.to_string(),
+        RepairOutcome::AlreadyTerminates(acc) => format!("program already terminates, with acc {acc}"),
+        RepairOutcome::Unrepairable => "no single flipped instruction lets the program terminate".to_string(),
+    }
+}
+
+/// `day8-repair <input>`: finds the one `jmp` or `nop` to swap so the program terminates.
+pub fn repair_command(args:&[String]) -> Result<(),String> {
+    let [input_path] = args else {
+        return Err("Usage: day8-repair <input>".into());
+    };
+    let program = load_program(input_path)?;
+    match repair(&program) {
+        RepairOutcome::Repaired(repair) => {
+            println!("Changed instruction {} (line {}): {} -> {}",repair.index,repair.index+1,
+                repair.original.to_string(),repair.replacement.to_string());
+            println!("Final acc: {}",repair.acc);
+        },
+        RepairOutcome::AlreadyTerminates(acc) => {
+            println!("The program already terminates; nothing to change.");
+            println!("Final acc: {acc}");
+        },
+        RepairOutcome::Unrepairable => return Err("No single flipped instruction lets the program terminate.".into()),
+    }
+    Ok(())
 }
 
 pub fn run_day(input_path:&str) {
@@ -114,4 +443,35 @@ pub fn run_day(input_path:&str) {
     let two = star_two(&input);
 
     println!("Day 8.\nStar one: {one}\nStar two: {two}")
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn program(lines:&[&str]) -> Vec<Instruction> {
+        lines.iter().map(|line| Instruction::from_str(line).unwrap_or_else(|e| panic!("<{line}>: {e}"))).collect()
+    }
+
+    #[test]
+    fn repairs_the_example() {
+        let example = program(&["nop +0","acc +1","jmp +4","acc +3","jmp -3","acc -99","acc +1","jmp -4","acc +6"]);
+        let RepairOutcome::Repaired(repair) = repair(&example) else {
+            panic!("The example should be repaired.");
+        };
+        assert_eq!(repair.index, 7);
+        assert_eq!(repair.replacement.to_string(), "NOP (-4)");
+        assert_eq!(repair.acc, 8);
+        assert_eq!(star_one(&example), "5");
+    }
+
+    #[test]
+    fn leaves_terminating_programs_alone() {
+        assert!(matches!(repair(&program(&["nop +1","acc +2"])), RepairOutcome::AlreadyTerminates(2)));
+        assert!(matches!(repair(&program(&["nop +0","acc +1"])), RepairOutcome::AlreadyTerminates(1)));
+    }
+
+    #[test]
+    fn reports_unrepairable_programs() {
+        assert!(matches!(repair(&program(&["acc +1","jmp -1","jmp -2"])), RepairOutcome::Unrepairable));
+    }
+}
@@ -1,5 +1,8 @@
 use std::fs;
 use std::cmp;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::str::FromStr;
 use std::sync::atomic::{AtomicBool,Ordering};
 
 static VERBOSE: AtomicBool = AtomicBool::new(false);
@@ -19,79 +22,302 @@ fn setup(input_file:&str) -> Vec<String> {
     return retval;
 }
 
-fn seat_id(input:&str,verbose:bool) -> u32 {
-    let mut max:u32 = 127;
-    let mut min:u32 = 0;
-    for instruction in input[..7].chars() {
-        let middle = min + ((max - min) / 2);
-        if instruction == 'F' {
-            max = middle;
-        } else {
-            min = middle + 1;
+/// How many rows and columns a plane has, as a number of bits each. A boarding pass spells
+/// out the row bits with F (front, 0) and B (back, 1), then the column bits with L (left, 0)
+/// and R (right, 1), most significant bit first.
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+struct PlaneLayout {
+    row_bits:u32,
+    col_bits:u32,
+}
+
+impl Default for PlaneLayout {
+    fn default() -> Self {
+        Self { row_bits: 7, col_bits: 3 }
+    }
+}
+
+impl PlaneLayout {
+    fn new(row_bits:u32, col_bits:u32) -> Result<Self,String> {
+        if row_bits.checked_add(col_bits).is_none_or(|bits| bits > 31) {
+            return Err(format!("{row_bits} row bits and {col_bits} column bits do not fit a seat id."));
         }
+        Ok(Self { row_bits, col_bits })
+    }
+
+    fn rows(&self) -> u32 {
+        1 << self.row_bits
+    }
+
+    fn cols(&self) -> u32 {
+        1 << self.col_bits
+    }
+
+    /// Decodes a pass by halving the range of rows, then columns, once per letter.
+    /// When verbose, the range left after every letter is printed.
+    fn decode(&self, code:&str, verbose:bool) -> Result<BoardingPass,String> {
+        let letters:Vec<char> = code.trim().chars().collect();
+        let expected = (self.row_bits + self.col_bits) as usize;
+        if letters.len() != expected {
+            return Err(format!("<{code}> has {} letters, expected {expected}.",letters.len()));
+        }
+        let (row_letters, col_letters) = letters.split_at(self.row_bits as usize);
+        let row = Self::halve(row_letters, self.rows() - 1, ('F','B'), verbose)
+            .map_err(|letter| format!("<{code}>: expected F or B for the row, found {letter}."))?;
+        let col = Self::halve(col_letters, self.cols() - 1, ('L','R'), verbose)
+            .map_err(|letter| format!("<{code}>: expected L or R for the column, found {letter}."))?;
         if verbose {
-            print!("({};{})",min,max);
+            println!();
         }
+        Ok(BoardingPass { row, col, layout: *self })
     }
-    let row = min;
-    max = 7;
-    min = 0;
-    for instruction in input[7..].chars() {
-        let middle = min + ((max-min)/2);
-        if instruction == 'R' {
-            min = middle + 1;
-        } else {
-            max = middle;
+
+    fn halve(letters:&[char], mut max:u32, (lower,upper):(char,char), verbose:bool) -> Result<u32,char> {
+        let mut min:u32 = 0;
+        for letter in letters {
+            let middle = min + ((max - min) / 2);
+            if *letter == lower {
+                max = middle;
+            } else if *letter == upper {
+                min = middle + 1;
+            } else {
+                return Err(*letter);
+            }
+            if verbose {
+                print!("({min};{max})");
+            }
         }
-        if verbose {
-            print!("({min};{max})");
+        Ok(min)
+    }
+
+    fn encode(&self, row:u32, col:u32) -> Result<BoardingPass,String> {
+        if row >= self.rows() || col >= self.cols() {
+            return Err(format!("Row {row}, column {col} is outside a plane of {} rows and {} columns.",self.rows(),self.cols()));
         }
+        Ok(BoardingPass { row, col, layout: *self })
     }
-    let col = min;
-    if verbose {
-        println!("");
+}
+
+#[derive(Clone,Copy,PartialEq,Eq,Debug)]
+struct BoardingPass {
+    row:u32,
+    col:u32,
+    layout:PlaneLayout,
+}
+
+impl BoardingPass {
+    fn row(&self) -> u32 {
+        self.row
+    }
+
+    fn col(&self) -> u32 {
+        self.col
+    }
+
+    fn id(&self) -> u32 {
+        self.row * self.layout.cols() + self.col
+    }
+}
+
+impl FromStr for BoardingPass {
+    type Err = String;
+
+    /// Decodes a pass for the default 7+3 layout.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PlaneLayout::default().decode(s, false)
+    }
+}
+
+impl Display for BoardingPass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for bit in (0..self.layout.row_bits).rev() {
+            write!(f,"{}",if (self.row >> bit) & 1 == 1 { 'B' } else { 'F' })?;
+        }
+        for bit in (0..self.layout.col_bits).rev() {
+            write!(f,"{}",if (self.col >> bit) & 1 == 1 { 'R' } else { 'L' })?;
+        }
+        Ok(())
+    }
+}
+
+/// Decodes every pass, leaving out (and, when verbose, reporting) the ones that don't decode.
+fn decode_all(input:&[String], layout:&PlaneLayout, verbose:bool) -> Vec<BoardingPass> {
+    let mut retval = Vec::new();
+    for sequence in input {
+        match layout.decode(sequence, verbose) {
+            Ok(pass) => retval.push(pass),
+            Err(reason) => if verbose {
+                println!("Skipping pass: {reason}");
+            },
+        }
+    }
+    retval
+}
+
+/// A run of free seats, with the occupied seats on either side.
+struct Gap {
+    before:u32,
+    after:u32,
+}
+
+impl Gap {
+    fn free_seats(&self) -> std::ops::Range<u32> {
+        self.before+1..self.after
+    }
+}
+
+/// Which seats of a plane are taken, according to a stack of boarding passes.
+struct Occupancy {
+    layout:PlaneLayout,
+    /// Seat id, mapped to the number of passes for that seat.
+    seats:BTreeMap<u32,usize>,
+    /// Passes that do not point at a seat of this plane, and why.
+    invalid:Vec<String>,
+}
+
+impl Occupancy {
+    fn new(input:&[String], layout:PlaneLayout) -> Self {
+        let mut seats = BTreeMap::new();
+        let mut invalid = Vec::new();
+        for sequence in input {
+            match layout.decode(sequence, false) {
+                Ok(pass) => *seats.entry(pass.id()).or_insert(0) += 1,
+                Err(reason) => invalid.push(reason),
+            }
+        }
+        Occupancy { layout, seats, invalid }
+    }
+
+    fn pass_for(&self, id:u32) -> BoardingPass {
+        BoardingPass { row: id / self.layout.cols(), col: id % self.layout.cols(), layout: self.layout }
+    }
+
+    /// Every run of free seats between two taken seats, front to back.
+    fn gaps(&self) -> Vec<Gap> {
+        let taken:Vec<u32> = self.seats.keys().copied().collect();
+        taken.windows(2)
+            .filter(|win| win[0]+1 != win[1])
+            .map(|win| Gap { before: win[0], after: win[1] })
+            .collect()
+    }
+
+    /// Seats with more than one pass, and how many passes each.
+    fn duplicates(&self) -> Vec<(BoardingPass,usize)> {
+        self.seats.iter()
+            .filter(|(_,count)| **count > 1)
+            .map(|(id,count)| (self.pass_for(*id),*count))
+            .collect()
+    }
+
+    /// The plane, row by row: `#` for a taken seat, `.` for a free one.
+    fn render(&self) -> String {
+        let mut retval = String::new();
+        let row_width = (self.layout.rows() - 1).to_string().len();
+        for row in 0..self.layout.rows() {
+            retval.push_str(&format!("{row:>row_width$} "));
+            for col in 0..self.layout.cols() {
+                let id = row * self.layout.cols() + col;
+                retval.push(if self.seats.contains_key(&id) { '#' } else { '.' });
+            }
+            retval.push('\n');
+        }
+        retval
     }
-    return (row * 8) + col;
 }
 
 fn star_one(input:&Vec<String>) -> String {
     let mut retval = 0;
     let verbose = VERBOSE.fetch_and(true, Ordering::Acquire);
-    for sequence in input {
-        if verbose {
-            println!("Parsing pass {sequence}.")
-        }
-        //Column is now col_min.
-        let seat_id = seat_id(&sequence,verbose);
-        retval = cmp::max(retval, seat_id);
+    for pass in decode_all(input, &PlaneLayout::default(), verbose) {
+        retval = cmp::max(retval, pass.id());
         if verbose {
-            println!("\nInput {} yields seat id {seat_id} (row {}, col {})",String::from(sequence),seat_id/8,seat_id%8);
+            println!("Pass {pass} yields seat id {} (row {}, col {})",pass.id(),pass.row(),pass.col());
         }
     }
     return retval.to_string();
 }
 
 fn star_two(input:&Vec<String>) -> String {
-    let mut numbers:Vec<u32> = Vec::new();
     let verbose = VERBOSE.fetch_and(true, Ordering::Acquire);
-    for pass in input.iter() {
-        numbers.push(seat_id(pass, verbose));
-    }
-    numbers.sort();
+    let occupancy = Occupancy::new(input, PlaneLayout::default());
     if verbose {
-        println!("Found {} numbers; first {} and last {}",numbers.len(),numbers[0],numbers[numbers.len()-1]);
+        println!("Found {} taken seats and {} invalid passes.",occupancy.seats.len(),occupancy.invalid.len());
     }
-    let mut retval:u32 = 0;
-    for win in numbers.windows(2) {
-        if win[0]+1 != win[1] {
-            retval = win[0]+1;
-            break;
-        }
+    //Our seat is the only free one with a taken seat on either side.
+    match occupancy.gaps().iter().find(|gap| gap.free_seats().len() == 1) {
+        Some(gap) => gap.free_seats().start.to_string(),
+        None => "no free seat between two taken seats".to_string(),
     }
-    retval.to_string()
 }
 
 
+/// Pulls `--layout <row_bits>,<col_bits>` out of the arguments; the 7+3 layout otherwise.
+fn layout_argument(args:&[String]) -> Result<(PlaneLayout,Vec<String>),String> {
+    let Some(position) = args.iter().position(|arg| arg == "--layout") else {
+        return Ok((PlaneLayout::default(),args.to_vec()));
+    };
+    let value = args.get(position+1).ok_or("--layout needs <row_bits>,<col_bits>.")?;
+    let (row_bits, col_bits) = value.split_once(',').ok_or(format!("Expected <row_bits>,<col_bits>, found <{value}>."))?;
+    let row_bits = row_bits.parse::<u32>().map_err(|_| format!("Could not parse <{row_bits}>."))?;
+    let col_bits = col_bits.parse::<u32>().map_err(|_| format!("Could not parse <{col_bits}>."))?;
+    let mut rest = args.to_vec();
+    rest.drain(position..position+2);
+    Ok((PlaneLayout::new(row_bits, col_bits)?,rest))
+}
+
+/// `day5-pass [--layout <row_bits>,<col_bits>] <code|row,col>...`: decodes boarding passes,
+/// or encodes seats into boarding passes.
+pub fn pass_command(args:&[String]) -> Result<(),String> {
+    let (layout, args) = layout_argument(args)?;
+    if args.is_empty() {
+        return Err("Usage: day5-pass [--layout <row_bits>,<col_bits>] <code|row,col>...".into());
+    }
+    for arg in args.iter() {
+        let pass = match arg.split_once(',') {
+            Some((row, col)) => {
+                let row = row.parse::<u32>().map_err(|_| format!("Could not parse row <{row}>."))?;
+                let col = col.parse::<u32>().map_err(|_| format!("Could not parse column <{col}>."))?;
+                layout.encode(row, col)?
+            },
+            None => layout.decode(arg, false)?,
+        };
+        println!("{pass}: row {}, column {}, seat ID {}",pass.row(),pass.col(),pass.id());
+    }
+    Ok(())
+}
+
+/// `day5-seats <input> [--layout <row_bits>,<col_bits>]`: draws the plane, and lists every run
+/// of free seats, every seat with several passes, and every pass that is not for this plane.
+pub fn seats_command(args:&[String]) -> Result<(),String> {
+    let (layout, args) = layout_argument(args)?;
+    let [input_path] = &args[..] else {
+        return Err("Usage: day5-seats <input> [--layout <row_bits>,<col_bits>]".into());
+    };
+    let input_str = fs::read_to_string(input_path).map_err(|e| format!("Could not read {input_path}: {e}"))?;
+    let input:Vec<String> = input_str.lines().filter(|line| !line.trim().is_empty()).map(String::from).collect();
+    let occupancy = Occupancy::new(&input, layout);
+
+    print!("{}",occupancy.render());
+    println!("{} of {} seats taken.",occupancy.seats.len(),layout.rows() * layout.cols());
+    let gaps = occupancy.gaps();
+    println!("{} gap(s) between taken seats:",gaps.len());
+    for gap in gaps.iter() {
+        let free = gap.free_seats();
+        println!("  {} free seat(s), {} to {}; between {} ({}) and {} ({})",free.len(),free.start,free.end-1,
+            gap.before,occupancy.pass_for(gap.before),gap.after,occupancy.pass_for(gap.after));
+    }
+    let duplicates = occupancy.duplicates();
+    println!("{} seat(s) with more than one pass:",duplicates.len());
+    for (pass,count) in duplicates.iter() {
+        println!("  {pass} (seat {}): {count} passes",pass.id());
+    }
+    println!("{} pass(es) not for this plane:",occupancy.invalid.len());
+    for reason in occupancy.invalid.iter() {
+        println!("  {reason}");
+    }
+    Ok(())
+}
+
 pub fn run_day(input_file:&str) {
     let parsed_input = setup(input_file);
     let one = star_one(&parsed_input);
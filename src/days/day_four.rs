@@ -1,107 +1,123 @@
-use std::{collections::HashMap, fs};
+use std::{collections::{BTreeMap, HashMap}, fs};
 
+use crate::common::records::read_records;
+use crate::common::schema::{FailureKind, FieldFailure, Schema};
 
-fn setup(input_path:&str) -> Vec<HashMap<String,String>> {
-    let input_string = fs::read_to_string(input_path).expect("Could not read file.");
-    let mut retval:Vec<HashMap<String,String>> = Vec::new();
-    let mut current:HashMap<String,String> = HashMap::new();
-    for line in input_string.lines() {
-        if line == "" {
-            retval.push(current);
-            current = HashMap::new();
-        }
-        for phrase in line.split(' '){
-            if phrase == "" {
-                continue;
-            }
-            let colon = phrase.find(':').expect(&format!("Malformed input <{phrase}>"));
-            current.insert(String::from(&phrase[..colon]), String::from(&phrase[colon+1 ..]));
-        }
+/// The North Pole passport rules of star two; `cid` is optional for both stars.
+const PASSPORT_SCHEMA:&str = "\
+byr required int 1920..2002 digits=4 -- Birth year must be a four-digit year from 1920 to 2002.
+iyr required int 2010..2020 digits=4 -- Issue year must be a four-digit year from 2010 to 2020.
+eyr required int 2020..2030 digits=4 -- Expiration year must be a four-digit year from 2020 to 2030.
+hgt required unit cm=150..193 in=59..76 -- Height must be 150-193cm or 59-76in.
+hcl required hex 6 -- Hair colour must be # followed by six hexadecimal digits.
+ecl required enum amb blu brn gry grn hzl oth -- Eye colour must be one of amb, blu, brn, gry, grn, hzl or oth.
+pid required regex [0-9]{9} -- Passport ID must be nine digits.
+cid optional any
+";
+
+/// A field without a `:` between its name and value.
+struct MalformedField {
+    line_number:usize,
+    phrase:String,
+}
+
+impl std::fmt::Display for MalformedField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f,"Line {}: malformed field <{}>",self.line_number,self.phrase)
     }
-    return retval
 }
 
-fn star_one(parsed_input:&Vec<HashMap<String,String>>) -> String {
-    let expected_keys = ["byr","iyr","eyr","hgt","hcl","ecl","pid","cid"];
-    let mut retval = 0;
-    'outer_s1: for passport in parsed_input.iter() {
-        for key in expected_keys {
-            if !passport.contains_key(key) {
-                if key != "cid" {
-                    continue 'outer_s1;
+fn parse_input(input_string:&str) -> (Vec<HashMap<String,String>>,Vec<MalformedField>) {
+    let mut retval:Vec<HashMap<String,String>> = Vec::new();
+    let mut malformed:Vec<MalformedField> = Vec::new();
+    for record in read_records(input_string) {
+        let mut current:HashMap<String,String> = HashMap::new();
+        for (line_number,line) in record.numbered_lines() {
+            for phrase in line.split_whitespace() {
+                match phrase.split_once(':') {
+                    Some((key,value)) => {
+                        current.insert(String::from(key), String::from(value));
+                    },
+                    None => malformed.push(MalformedField { line_number, phrase: phrase.to_string() }),
                 }
             }
         }
-        retval += 1;
+        retval.push(current);
     }
-    return retval.to_string();
+    (retval,malformed)
 }
 
-fn star_two(parsed_input:&Vec<HashMap<String,String>>) -> String {
-    type VerFunc = fn(&String) -> bool;
-    let mut verifications:HashMap<String,VerFunc> = HashMap::new();
-    verifications.insert("byr".to_string(),|l| {
-        l.len() == 4 && {
-            let ynum = l.parse::<u32>().unwrap_or(0); 
-            ynum >= 1920 && ynum <= 2002
-        }
-    });
-    verifications.insert("iyr".to_string(),|l| {
-        l.len() == 4 && {
-            let ynum = l.parse::<u32>().unwrap_or(0);
-            ynum >= 2010 && ynum <= 2020
-        }
-    });
-    verifications.insert("eyr".to_string(), |l| {
-        l.len() == 4 && {
-            let ynum = l.parse::<u32>().unwrap_or(0);
-            ynum >= 2020 && ynum <= 2030
-        }
-    });
-    verifications.insert("hgt".to_string(), |l| {
-        if l.len() < 3 {
-            return false;
-        }
-        let height = &l[..l.len()-2];
-        let height = height.parse::<u32>().unwrap_or(0);
-        if l[l.len()-2 ..] == "in"[..] {
-            height >= 59 && height <= 76
-        } else if l[l.len()-2 ..] == "cm"[..] {
-            height >= 150 && height <= 193
-        } else {
-            false
-        }
-    });
-    verifications.insert("hcl".to_string(),|l| {
-        let hex_digits:Vec<char> = "0123456789abcdef".chars().collect();
-        let mut chars = l.chars();
-        l.len() == 7 && chars.next().or(Some('!')).unwrap() == '#' && chars.all(|c| hex_digits.contains(&c))
-    });
-    verifications.insert("ecl".to_string(), |l| {
-        let valid_colors = ["amb","blu","brn","gry","grn","hzl","oth"];
-        valid_colors.contains(&&l[..]) //Don't really get why the double-ref is showing up...
-    });
-    verifications.insert("pid".to_string(),|l| {
-        l.len() == 9 && l.chars().all(|c| "0123456789".contains(c))
-    });
+fn setup(input_path:&str) -> (Vec<HashMap<String,String>>,Vec<MalformedField>) {
+    let input_string = fs::read_to_string(input_path).expect("Could not read file.");
+    parse_input(&input_string)
+}
 
-    let mut retval:u32 = 0;
-    'outer_s2: for passport in parsed_input.iter() {
-        for (field, verify) in verifications.iter(){
-            if !verify(passport.get(field).unwrap_or(&"".to_string())) {
-                continue 'outer_s2;
-            }
-        }
-        retval += 1;
-    }
+fn star_one(parsed_input:&Vec<HashMap<String,String>>, schema:&Schema) -> String {
+    parsed_input.iter()
+        .filter(|passport| schema.has_required_fields(passport))
+        .count()
+        .to_string()
+}
+
+fn star_two(parsed_input:&Vec<HashMap<String,String>>, schema:&Schema) -> String {
+    parsed_input.iter()
+        .filter(|passport| schema.is_valid(passport))
+        .count()
+        .to_string()
+}
+
+/// Every rejected field of every record; records without failures are valid.
+fn diagnose_all(parsed_input:&[HashMap<String,String>], schema:&Schema) -> Vec<Vec<FieldFailure>> {
+    parsed_input.iter().map(|record| schema.diagnose(record)).collect()
+}
 
-    return retval.to_string();
+fn count_by_kind(diagnoses:&[Vec<FieldFailure>]) -> BTreeMap<FailureKind,usize> {
+    let mut retval = BTreeMap::new();
+    for failure in diagnoses.iter().flatten() {
+        *retval.entry(failure.kind).or_insert(0) += 1;
+    }
+    retval
 }
 
+/// `day4-validate <input> [schema]`: checks every record against a schema file, or against
+/// the passport rules if no schema is given, and lists every rejected field.
+pub fn validate_command(args:&[String]) -> Result<(),String> {
+    let (input_path, schema) = match args {
+        [input_path] => (input_path, Schema::parse(PASSPORT_SCHEMA)?),
+        [input_path, schema_path] => (input_path, Schema::load(schema_path)?),
+        _ => return Err("Usage: day4-validate <input> [schema]".into()),
+    };
+    let input_string = fs::read_to_string(input_path).map_err(|e| format!("Could not read {input_path}: {e}"))?;
+    let (parsed_input,malformed) = parse_input(&input_string);
+    for field in malformed.iter() {
+        println!("{field}");
+    }
+    let diagnoses = diagnose_all(&parsed_input, &schema);
+    for (index,failures) in diagnoses.iter().enumerate() {
+        if failures.is_empty() {
+            println!("Record {}: valid",index+1);
+            continue;
+        }
+        println!("Record {}: invalid",index+1);
+        for failure in failures {
+            println!("  {failure}");
+        }
+    }
+    let valid = diagnoses.iter().filter(|failures| failures.is_empty()).count();
+    println!("{valid} of {} records are valid.",parsed_input.len());
+    for (kind,count) in count_by_kind(&diagnoses) {
+        println!("  {kind}: {count}");
+    }
+    Ok(())
+}
 
 pub fn run_day(input_path:&str) {
-    let parsed_input = setup(input_path);
-    let one = star_one(&parsed_input);
-    let two = star_two(&parsed_input);
+    let (parsed_input,malformed) = setup(input_path);
+    for field in malformed.iter() {
+        eprintln!("{field}");
+    }
+    let schema = Schema::parse(PASSPORT_SCHEMA).expect("Built-in passport schema should parse.");
+    let one = star_one(&parsed_input, &schema);
+    let two = star_two(&parsed_input, &schema);
     println!("Day 4.\nStar one: {one}\nStar two: {two}");
 }
\ No newline at end of file
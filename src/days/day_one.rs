@@ -1,56 +1,304 @@
-use std::fs;
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
 
-fn setup(input_path: &str) -> Vec<u32> {
+/// A line of an expense report that is not a number.
+struct BadLine {
+    line_number:usize,
+    content:String,
+}
+
+impl Display for BadLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f,"Line {}: could not parse <{}>",self.line_number,self.content)
+    }
+}
+
+/// Parses one line of an expense report. Blank lines hold no entry.
+fn parse_line(line_number:usize, line:&str) -> Result<Option<u64>,BadLine> {
+    let line = line.trim();
+    if line.is_empty() {
+        return Ok(None);
+    }
+    match line.parse::<u64>() {
+        Ok(number) => Ok(Some(number)),
+        Err(_) => Err(BadLine { line_number, content: line.to_string() }),
+    }
+}
+
+fn setup(input_path: &str) -> (Vec<u64>,Vec<BadLine>) {
     let input_content = fs::read_to_string(input_path).expect("Error reading input file.");
-    let mut retval:Vec<u32> = Vec::new();
-    for number in input_content.lines(){
-        if let Ok(num) = number.parse::<u32>() {
-            retval.push(num);
+    let mut retval:Vec<u64> = Vec::new();
+    let mut bad_lines:Vec<BadLine> = Vec::new();
+    for (index,line) in input_content.lines().enumerate() {
+        match parse_line(index+1, line) {
+            Ok(Some(number)) => retval.push(number),
+            Ok(None) => (),
+            Err(bad) => bad_lines.push(bad),
         }
     };
+    (retval,bad_lines)
+}
+
+/// Finds every distinct set of `k` entries that add up to `target`, as tuples of indices
+/// into `numbers`. Indices are ascending within a tuple, and the tuples are sorted.
+fn k_sum(numbers:&[u64], k:usize, target:u64) -> Vec<Vec<usize>> {
+    let mut sorted:Vec<(u64,usize)> = numbers.iter()
+        .enumerate()
+        .map(|(index,number)| (*number,index))
+        .collect();
+    sorted.sort();
+
+    let mut retval:Vec<Vec<usize>> = Vec::new();
+    let mut chosen:Vec<usize> = Vec::with_capacity(k);
+    k_sum_from(&sorted, k, target, &mut chosen, &mut retval);
+    for tuple in retval.iter_mut() {
+        tuple.sort();
+    }
+    retval.sort();
     retval
 }
 
-fn first_star(numbers:&Vec<u32>) -> String {
-    assert!(numbers.len() >= 2);
-    let end = numbers.len()-1;
-    for start in 0..end {
-        let one = numbers[start];
-        for two in numbers[start+1..].iter() {
-            let sum = one + *two;
-            if sum == 2020 {
-                let product = one * *two;
-                return product.to_string();
+/// Fixes the smallest remaining entry and recurses, until only a two-sum is left.
+fn k_sum_from(sorted:&[(u64,usize)], k:usize, target:u64, chosen:&mut Vec<usize>, found:&mut Vec<Vec<usize>>) {
+    match k {
+        0 => {
+            if target == 0 {
+                found.push(chosen.clone());
             }
-        }
+        },
+        1 => {
+            let start = sorted.partition_point(|(value,_)| *value < target);
+            for (_,index) in sorted[start..].iter().take_while(|(value,_)| *value == target) {
+                chosen.push(*index);
+                found.push(chosen.clone());
+                chosen.pop();
+            }
+        },
+        2 => two_sum(sorted, target, chosen, found),
+        _ => {
+            for (position,(value,index)) in sorted.iter().enumerate() {
+                //Everything after this entry is at least as large, so k of them can't fit anymore.
+                if value.checked_mul(k as u64).is_none_or(|smallest| smallest > target) {
+                    break;
+                }
+                chosen.push(*index);
+                k_sum_from(&sorted[position+1..], k-1, target - value, chosen, found);
+                chosen.pop();
+            }
+        },
     }
-    "ERROR".to_string()
-}
-
-fn second_star(numbers:&Vec<u32>)->String {
-    assert!(numbers.len() >= 3);
-    let end = numbers.len()-2;
-    for first in 0..end {
-        let one = numbers[first];
-        for second in first+1 .. end+1{
-            let two = numbers[second];
-            for three in numbers[second+1 ..].iter(){
-                let sum = one + two + *three;
-                if sum == 2020 {
-                    let product = one * two * *three;
-                    return product.to_string();
+}
+
+/// Two-pointer search over sorted entries. Runs of equal values yield every pairing.
+fn two_sum(sorted:&[(u64,usize)], target:u64, chosen:&mut Vec<usize>, found:&mut Vec<Vec<usize>>) {
+    if sorted.len() < 2 {
+        return;
+    }
+    let mut push_pair = |a:usize, b:usize| {
+        chosen.push(a);
+        chosen.push(b);
+        found.push(chosen.clone());
+        chosen.truncate(chosen.len()-2);
+    };
+
+    let mut low = 0;
+    let mut high = sorted.len()-1;
+    while low < high {
+        let (low_value, high_value) = (sorted[low].0, sorted[high].0);
+        let sum = u128::from(low_value) + u128::from(high_value);
+        if sum < u128::from(target) {
+            low += 1;
+        } else if sum > u128::from(target) {
+            high -= 1;
+        } else if low_value == high_value {
+            //Everything in between has the same value, so any two of them will do.
+            for a in low..high {
+                for b in a+1..=high {
+                    push_pair(sorted[a].1, sorted[b].1);
                 }
             }
+            break;
+        } else {
+            let low_end = low + sorted[low..].iter().take_while(|(value,_)| *value == low_value).count();
+            let high_start = high + 1 - sorted[..=high].iter().rev().take_while(|(value,_)| *value == high_value).count();
+            for a in low..low_end {
+                for b in high_start..=high {
+                    push_pair(sorted[a].1, sorted[b].1);
+                }
+            }
+            low = low_end;
+            high = high_start - 1;
+        }
+    }
+}
+
+fn product_of_first(numbers:&[u64], k:usize, target:u64) -> String {
+    match k_sum(numbers, k, target).first() {
+        Some(indices) => indices.iter()
+            .try_fold(1u128, |acc, index| acc.checked_mul(u128::from(numbers[*index])))
+            .map_or("product does not fit in 128 bits".to_string(), |product| product.to_string()),
+        None => format!("no {k} entries add up to {target}"),
+    }
+}
+
+/// Outcome of searching an expense report for a pair, one line at a time.
+struct StreamedPair {
+    pair:Option<(u64,u64)>,
+    lines_read:usize,
+    bad_lines:Vec<BadLine>,
+}
+
+/// Reads entries one line at a time, and stops as soon as an entry's complement has been seen
+/// before. Only entries that could still be part of a pair are remembered.
+fn stream_pair<R:BufRead>(reader:R, target:u64) -> io::Result<StreamedPair> {
+    let mut seen:HashSet<u64> = HashSet::new();
+    let mut bad_lines:Vec<BadLine> = Vec::new();
+    let mut lines_read = 0;
+    for (index,line) in reader.lines().enumerate() {
+        let line = line?;
+        lines_read = index + 1;
+        match parse_line(lines_read, &line) {
+            Ok(Some(number)) if number <= target => {
+                if seen.contains(&(target - number)) {
+                    return Ok(StreamedPair { pair: Some((target - number, number)), lines_read, bad_lines });
+                }
+                seen.insert(number);
+            },
+            Ok(_) => (),
+            Err(bad) => bad_lines.push(bad),
         }
     }
+    Ok(StreamedPair { pair: None, lines_read, bad_lines })
+}
+
+fn first_star(numbers:&Vec<u64>) -> String {
+    product_of_first(numbers, 2, 2020)
+}
 
-    "ERROR".to_string()
+fn second_star(numbers:&Vec<u64>) -> String {
+    product_of_first(numbers, 3, 2020)
+}
+
+/// `day1-ksum <input> <k> <target>`: lists every set of k entries adding up to the target.
+pub fn k_sum_command(args:&[String]) -> Result<(),String> {
+    let usage = "Usage: day1-ksum <input> <k> <target>";
+    let [input_path, k, target] = args else {
+        return Err(usage.into());
+    };
+    let k:usize = k.parse().map_err(|_| format!("Could not parse k <{k}>. {usage}"))?;
+    let target:u64 = target.parse().map_err(|_| format!("Could not parse target <{target}>. {usage}"))?;
+    let (numbers,bad_lines) = setup(input_path);
+    for bad in bad_lines.iter() {
+        eprintln!("{bad}");
+    }
+    let solutions = k_sum(&numbers, k, target);
+    for indices in solutions.iter() {
+        let values:Vec<String> = indices.iter().map(|index| numbers[*index].to_string()).collect();
+        println!("{indices:?}: {}",values.join(" + "));
+    }
+    println!("{} solution(s) for {k} entries adding up to {target}.",solutions.len());
+    Ok(())
+}
+
+/// `day1-stream <input> [target]`: finds a pair adding up to the target (2020 by default)
+/// without loading the whole report. Use `-` as the input to read from stdin.
+pub fn stream_command(args:&[String]) -> Result<(),String> {
+    let usage = "Usage: day1-stream <input> [target]";
+    let (input_path, target) = match args {
+        [input_path] => (input_path, 2020),
+        [input_path, target] => (input_path, target.parse::<u64>().map_err(|_| format!("Could not parse target <{target}>. {usage}"))?),
+        _ => return Err(usage.into()),
+    };
+    let result = if input_path == "-" {
+        stream_pair(io::stdin().lock(), target)
+    } else {
+        let file = File::open(input_path).map_err(|e| format!("Could not open {input_path}: {e}"))?;
+        stream_pair(BufReader::new(file), target)
+    }.map_err(|e| format!("Could not read {input_path}: {e}"))?;
+
+    for bad in result.bad_lines.iter() {
+        eprintln!("{bad}");
+    }
+    match result.pair {
+        Some((one,two)) => println!("{one} + {two} = {target} after {} lines; product {}.",result.lines_read,u128::from(one) * u128::from(two)),
+        None => println!("No pair adds up to {target} in {} lines.",result.lines_read),
+    }
+    Ok(())
 }
 
 pub fn run_day(input_path: &str) {
-    let numbers = setup(input_path);
+    let (numbers,bad_lines) = setup(input_path);
+    for bad in bad_lines.iter() {
+        eprintln!("{bad}");
+    }
     let star_one = first_star(&numbers);
     let star_two = second_star(&numbers);
     println!("day 1.\nStar one: {star_one}\nStar two: {star_two}");
 
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE:[u64;6] = [1721, 979, 366, 299, 675, 1456];
+
+    /// Every ascending tuple of k indices whose entries add up to the target, by trying them all.
+    fn brute_force(numbers:&[u64], k:usize, target:u64) -> Vec<Vec<usize>> {
+        fn extend(numbers:&[u64], k:usize, target:u128, start:usize, chosen:&mut Vec<usize>, found:&mut Vec<Vec<usize>>) {
+            if chosen.len() == k {
+                if chosen.iter().map(|index| u128::from(numbers[*index])).sum::<u128>() == target {
+                    found.push(chosen.clone());
+                }
+                return;
+            }
+            for index in start..numbers.len() {
+                chosen.push(index);
+                extend(numbers, k, target, index+1, chosen, found);
+                chosen.pop();
+            }
+        }
+        let mut retval = Vec::new();
+        extend(numbers, k, u128::from(target), 0, &mut Vec::new(), &mut retval);
+        retval
+    }
+
+    #[test]
+    fn example_answers() {
+        assert_eq!(k_sum(&EXAMPLE, 2, 2020), vec![vec![0,3]]);
+        assert_eq!(k_sum(&EXAMPLE, 3, 2020), vec![vec![1,2,4]]);
+        assert_eq!(product_of_first(&EXAMPLE, 2, 2020), "514579");
+        assert_eq!(product_of_first(&EXAMPLE, 3, 2020), "241861950");
+        assert_eq!(product_of_first(&EXAMPLE, 4, 2020), "no 4 entries add up to 2020");
+    }
+
+    #[test]
+    fn equal_entries_pair_up_every_way() {
+        assert_eq!(k_sum(&[1010, 1010, 1010], 2, 2020), vec![vec![0,1], vec![0,2], vec![1,2]]);
+        assert_eq!(k_sum(&[5, 5, 5, 5], 3, 15), brute_force(&[5, 5, 5, 5], 3, 15));
+    }
+
+    #[test]
+    fn huge_entries_do_not_overflow() {
+        assert_eq!(k_sum(&[u64::MAX, u64::MAX, 1], 2, u64::MAX), Vec::<Vec<usize>>::new());
+        assert_eq!(k_sum(&[u64::MAX, 0, 3], 2, u64::MAX), vec![vec![0,1]]);
+        assert_eq!(k_sum(&[u64::MAX, u64::MAX, 0], 3, u64::MAX), Vec::<Vec<usize>>::new());
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        //Small values from a fixed linear congruential generator, so there are plenty of repeats.
+        let mut state:u64 = 2020;
+        let numbers:Vec<u64> = (0..14).map(|_| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (state >> 33) % 12
+        }).collect();
+        for k in 1..=4 {
+            for target in 0..40 {
+                assert_eq!(k_sum(&numbers, k, target), brute_force(&numbers, k, target), "k {k}, target {target}, numbers {numbers:?}");
+            }
+        }
+    }
+}
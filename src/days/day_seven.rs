@@ -1,149 +1,615 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
 use std::fs; 
+use std::io::{self, Write};
 use std::str::FromStr;
 
+use crate::common::parse::ParseError;
 
 struct BagRule {
     color:String,
     contains:Vec<(String,u32)>
 }
 
-impl FromStr for BagRule {
-    type Err = String;
+/// The words of a rule, each with the column it starts at. Words are separated by exactly one
+/// space; punctuation stays on the word it follows.
+struct RuleWords<'a> {
+    words:Vec<(usize,&'a str)>,
+    next:usize,
+    end_column:usize,
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let sep_position = s.find(" bags contain ");
-        if let None = sep_position {
-            return Err("Separator not found, string is malformed.".to_string());
+impl<'a> RuleWords<'a> {
+    fn new(line:&'a str) -> Result<Self,ParseError> {
+        let mut words = Vec::new();
+        let mut column = 1;
+        for word in line.split(' ') {
+            if word.is_empty() {
+                return Err(ParseError { column, message: "unexpected space".into() });
+            }
+            words.push((column,word));
+            column += word.chars().count() + 1;
         }
-        let sep_position = sep_position.unwrap();
-        let color = s[..sep_position].to_string();
-        let sep_end = sep_position + " bags contain ".len();
-        let mut contains:Vec<(String,u32)> = Vec::new();
-        if let Some(_) = s.find(" no ") {
-            return Ok(BagRule{color,contains});
+        Ok(RuleWords { words, next: 0, end_column: column - 1 })
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.words.get(self.next).map(|(_,word)| *word)
+    }
+
+    fn take(&mut self, expected:&str) -> Result<(usize,&'a str),ParseError> {
+        let retval = self.words.get(self.next).copied().ok_or_else(|| ParseError {
+            column: self.end_column,
+            message: format!("rule ends early, expected {expected}"),
+        })?;
+        self.next += 1;
+        Ok(retval)
+    }
+
+    fn expect(&mut self, wanted:&str) -> Result<(),ParseError> {
+        let (column,word) = self.take(&format!("<{wanted}>"))?;
+        if word != wanted {
+            return Err(ParseError { column, message: format!("expected <{wanted}>, found <{word}>") });
         }
-        for bag in s[sep_end..].split(", ") {
-            let mut end_offset = 4; 
-            let sub_split = bag.find(' ').unwrap();
-            let amount:u32 = bag[..sub_split].parse().unwrap();
-            if amount != 1 {
-                end_offset += 1;
+        Ok(())
+    }
+
+    /// One or more lowercase words, up to the word `bag` or `bags`.
+    fn colour(&mut self) -> Result<String,ParseError> {
+        let mut words:Vec<&str> = Vec::new();
+        while let Some(word) = self.peek() {
+            if matches!(word.trim_end_matches([',','.']), "bag" | "bags") {
+                break;
+            }
+            let (column,word) = self.take("a colour")?;
+            if !word.chars().all(|c| c.is_ascii_lowercase()) {
+                return Err(ParseError { column, message: format!("<{word}> is not part of a colour; expected lowercase letters") });
             }
-            if bag.ends_with('.') {
-                end_offset += 1;
+            words.push(word);
+        }
+        if words.is_empty() {
+            let column = self.words.get(self.next).map_or(self.end_column, |(column,_)| *column);
+            return Err(ParseError { column, message: "expected a colour".into() });
+        }
+        Ok(words.join(" "))
+    }
+}
+
+impl FromStr for BagRule {
+    type Err = ParseError;
+
+    /// Reads `<colour> bags contain <n> <colour> bag(s), ....` or
+    /// `<colour> bags contain no other bags.`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = RuleWords::new(s)?;
+        let color = words.colour()?;
+        words.expect("bags")?;
+        words.expect("contain")?;
+        let mut contains:Vec<(String,u32)> = Vec::new();
+        if words.peek() == Some("no") {
+            words.expect("no")?;
+            words.expect("other")?;
+            words.expect("bags.")?;
+        } else {
+            loop {
+                let (column,amount) = words.take("a number of bags")?;
+                let amount:u32 = match amount.parse() {
+                    Ok(amount) if amount > 0 => amount,
+                    _ => return Err(ParseError { column, message: format!("<{amount}> is not a number of bags") }),
+                };
+                let other_color = words.colour()?;
+                let noun = if amount == 1 { "bag" } else { "bags" };
+                let (column,word) = words.take(&format!("<{noun}>"))?;
+                let punctuation = word.strip_prefix(noun).filter(|rest| !rest.starts_with('s'))
+                    .ok_or_else(|| ParseError { column, message: format!("expected <{noun}>, found <{word}>") })?;
+                contains.push((other_color,amount));
+                match punctuation {
+                    "," => continue,
+                    "." => break,
+                    _ => return Err(ParseError {
+                        column: column + noun.len(),
+                        message: "expected <,> or <.> after the bag".into(),
+                    }),
+                }
             }
-            let other_color:String = bag[sub_split+1..bag.len()-end_offset].to_string();
-            contains.push((other_color,amount));
         }
-        return Ok(BagRule{color,contains});
+        if let Some((column,_)) = words.words.get(words.next) {
+            return Err(ParseError { column: *column, message: "unexpected text after the end of the rule".into() });
+        }
+        Ok(BagRule{color,contains})
     }
 }
 
-impl ToString for BagRule{
-    fn to_string(&self) -> String {
+/// Writes the rule back as it appears in the puzzle input.
+impl fmt::Display for BagRule {
+    fn fmt(&self, f:&mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} bags contain ", self.color)?;
         if self.contains.is_empty() {
-            let mut retval = String::from(&self.color);
-            retval.push_str(" bags contain no further bags.");
-            return retval;
+            return write!(f, "no other bags.");
         }
-        let mut retval = String::from("Bagging rule for ");
-        retval.push_str(&self.color);
-        retval.push_str(" bags: ");
-        for (color, amount) in &self.contains {
-            retval.push_str(&amount.to_string());
-            retval.push_str(" times a ");
-            retval.push_str(color);
-            retval.push_str(" bag, ");
+        for (index,(color,amount)) in self.contains.iter().enumerate() {
+            let separator = if index + 1 == self.contains.len() { "." } else { ", " };
+            let noun = if *amount == 1 { "bag" } else { "bags" };
+            write!(f, "{amount} {color} {noun}{separator}")?;
         }
-        retval.replace_range(retval.len()-2.., ".");
-        return retval;
+        Ok(())
     }
 }
 
-fn setup(input_path:&str) -> Vec<BagRule> {
-    let str_input = fs::read_to_string(input_path).expect("Could not read file.");
-    let mut retval:Vec<BagRule> = Vec::new();
-    for rule in str_input.lines(){
-        if let Ok(p_rule) = BagRule::from_str(rule) {
-            retval.push(p_rule);
+/// Reads every non-blank line as a rule. Lines that are not rules are left out, and described
+/// in the second list.
+fn parse_rules(input:&str) -> (Vec<BagRule>,Vec<String>) {
+    let mut rules:Vec<BagRule> = Vec::new();
+    let mut errors:Vec<String> = Vec::new();
+    for (index,line) in input.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() {
+            continue;
+        }
+        match BagRule::from_str(line) {
+            Ok(rule) => rules.push(rule),
+            Err(e) => errors.push(format!("Line {}, {e}",index+1)),
+        }
+    }
+    (rules,errors)
+}
+
+/// Reads the rules for one of the commands, failing on the first line that is not a rule.
+fn load_rules(input_path:&str) -> Result<Vec<BagRule>,String> {
+    let str_input = fs::read_to_string(input_path).map_err(|e| format!("Could not read {input_path}: {e}"))?;
+    let (rules,errors) = parse_rules(&str_input);
+    match errors.into_iter().next() {
+        Some(error) => Err(error),
+        None => Ok(rules),
+    }
+}
+
+enum BagError {
+    /// A colour with no rule of its own, and the colour whose rule mentions it, if any.
+    UndefinedColour { colour:String, inside:Option<String> },
+    /// Colours that end up inside themselves, outermost first, with the first repeated at the end.
+    Cycle(Vec<String>),
+    /// The count for this colour does not fit in 64 bits.
+    Overflow(String),
+}
+
+impl fmt::Display for BagError {
+    fn fmt(&self, f:&mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BagError::UndefinedColour { colour, inside: Some(inside) } =>
+                write!(f, "{inside} bags hold {colour} bags, but there is no rule for {colour} bags."),
+            BagError::UndefinedColour { colour, inside: None } => write!(f, "There is no rule for {colour} bags."),
+            BagError::Cycle(colours) => write!(f, "Bags hold themselves: {}.", colours.join(" > ")),
+            BagError::Overflow(colour) => write!(f, "{colour} bags hold too many bags to count."),
         }
     }
-    return retval;
 }
 
-fn star_one(input:&Vec<BagRule>) -> String {
-    //General idea: build a 'reverse graph' and collect all bags, starting from 'shiny gold'.
-    let mut reverse_tree:HashMap<&str,Vec<&str>> = HashMap::new();
-    for rule in input {
-        for (other,_) in &rule.contains {
-            let key = &(*other)[..];
-            if !reverse_tree.contains_key(key) {
-                reverse_tree.insert(&other,Vec::<&str>::new());
+/// The rules as a graph: an edge from each colour to every colour it directly contains.
+struct BagGraph<'a> {
+    children:HashMap<&'a str,Vec<(&'a str,u32)>>,
+    parents:HashMap<&'a str,Vec<&'a str>>,
+}
+
+impl<'a> BagGraph<'a> {
+    fn new(rules:&'a [BagRule]) -> Self {
+        let mut children:HashMap<&str,Vec<(&str,u32)>> = HashMap::new();
+        let mut parents:HashMap<&str,Vec<&str>> = HashMap::new();
+        for rule in rules {
+            let contents = children.entry(&rule.color).or_default();
+            for (other,amount) in &rule.contains {
+                contents.push((other,*amount));
+                parents.entry(other).or_default().push(&rule.color);
             }
-            reverse_tree.get_mut(key).expect("Should never happen!").push(&rule.color);
         }
-    };
-    let mut containing_bags:HashSet<&str> = HashSet::new();
-    let mut to_check = Vec::new();
-    to_check.push("shiny gold");
-    let mut next_round: HashSet<&str> = HashSet::new();
-    while !&to_check.is_empty() {
-        next_round.clear();
-        for item in to_check.iter() {
-            if let Some(container) = reverse_tree.get(*item) {
-                next_round.extend(container.iter());
+        BagGraph { children, parents }
+    }
+
+    /// Colours directly inside a bag of this colour, with how many of each.
+    fn children(&self, colour:&str) -> &[(&'a str,u32)] {
+        self.children.get(colour).map_or(&[], Vec::as_slice)
+    }
+
+    /// Colours that directly hold a bag of this colour.
+    fn parents(&self, colour:&str) -> &[&'a str] {
+        self.parents.get(colour).map_or(&[], Vec::as_slice)
+    }
+
+    /// Every colour that can end up holding a bag of this colour, however deeply nested.
+    fn ancestors(&self, colour:&str) -> HashSet<&'a str> {
+        let mut retval:HashSet<&str> = HashSet::new();
+        let mut to_check:Vec<&str> = self.parents(colour).to_vec();
+        while let Some(next) = to_check.pop() {
+            if retval.insert(next) {
+                to_check.extend(self.parents(next));
             }
         }
-        for checked in containing_bags.iter() {
-            next_round.remove(*checked);
+        retval
+    }
+
+    /// Every colour that can end up inside a bag of this colour, however deeply nested.
+    fn descendants(&self, colour:&str) -> HashSet<&'a str> {
+        let mut retval:HashSet<&str> = HashSet::new();
+        let mut to_check:Vec<&str> = self.children(colour).iter().map(|(child,_)| *child).collect();
+        while let Some(next) = to_check.pop() {
+            if retval.insert(next) {
+                to_check.extend(self.children(next).iter().map(|(child,_)| *child));
+            }
         }
-        containing_bags.extend(next_round.iter());
-        to_check.clear();
-        to_check.extend(next_round.iter());
+        retval
     }
 
-    containing_bags.len().to_string()
-}
+    /// How many bags a single bag of this colour holds in total, not counting itself. The count
+    /// for each colour is worked out once and reused wherever that colour turns up again.
+    fn total_contained(&self, colour:&str) -> Result<u64,BagError> {
+        let Some((colour,_)) = self.children.get_key_value(colour) else {
+            return Err(BagError::UndefinedColour { colour: colour.to_string(), inside: None });
+        };
+        self.count_inside(colour, &mut HashMap::new())
+    }
 
-fn star_two(input:&Vec<BagRule>) -> String {
-    //We've done backward traversal, time for forward traversal.
-    //Assuming no loops (as in, red contains blue contains red)
-    //Approach: Depth-first traversal until the first empty bag,
-    //track total with a stack. Recursion?
+    /// Depth-first with an explicit stack, so long chains of nested bags can't overflow the call
+    /// stack. Each frame is a colour being counted, the index of its next child, and the count
+    /// so far; the frames are also the path used to spot a bag that ends up inside itself.
+    fn count_inside(&self, colour:&'a str, memo:&mut HashMap<&'a str,u64>) -> Result<u64,BagError> {
+        let add = |total:u64, inside:u64, amount:u32, colour:&str| inside.checked_add(1)
+            .and_then(|bags| bags.checked_mul(u64::from(amount)))
+            .and_then(|bags| bags.checked_add(total))
+            .ok_or_else(|| BagError::Overflow(colour.to_string()));
 
-    //Need to build a forward tree this time.
-    let mut tree:HashMap<&str,Vec<(&str,u32)>> = HashMap::new();
-    for rule in input {
-        let mut containing = Vec::new();
-        for (other,count) in rule.contains.iter() {
-            containing.push((&other[..],*count));
+        let mut path:Vec<(&'a str,usize,u64)> = vec![(colour,0,0)];
+        let mut on_path:HashSet<&'a str> = HashSet::from([colour]);
+        while let Some(frame) = path.last_mut() {
+            let (current,index) = (frame.0,frame.1);
+            let contents = self.children(current);
+            let Some((child,amount)) = contents.get(index).copied() else {
+                //Every child is counted; hand the total to the bag holding this one.
+                let (current,_,total) = path.pop().expect("Frame was just looked at.");
+                on_path.remove(current);
+                memo.insert(current, total);
+                match path.last_mut() {
+                    Some(parent) => {
+                        let amount = self.children(parent.0)[parent.1 - 1].1;
+                        parent.2 = add(parent.2, total, amount, parent.0)?;
+                    },
+                    None => return Ok(total),
+                }
+                continue;
+            };
+            frame.1 += 1;
+            if let Some(known) = memo.get(child) {
+                frame.2 = add(frame.2, *known, amount, current)?;
+                continue;
+            }
+            if on_path.contains(child) {
+                let start = path.iter().position(|(outer,_,_)| *outer == child).expect("Colour is on the path.");
+                let cycle = path[start..].iter().map(|(c,_,_)| c.to_string()).chain([child.to_string()]).collect();
+                return Err(BagError::Cycle(cycle));
+            }
+            if !self.children.contains_key(child) {
+                return Err(BagError::UndefinedColour { colour: child.to_string(), inside: Some(current.to_string()) });
+            }
+            on_path.insert(child);
+            path.push((child,0,0));
         }
-        tree.insert(&rule.color,containing);
+        unreachable!("The outermost frame returns its total when it is done.")
     }
 
-    fn visit_bag(to_visit:&str,tree:&HashMap<&str,Vec<(&str,u32)>>) -> u32 {
-        let containing = tree.get(to_visit).unwrap();
-        if containing.is_empty() {
-            return 1;
+    /// Colours that some rule mentions, but that have no rule of their own.
+    fn undefined_colours(&self) -> Vec<BagError> {
+        let mut retval:Vec<BagError> = Vec::new();
+        for (colour,parents) in self.parents.iter() {
+            if !self.children.contains_key(colour) {
+                for parent in parents {
+                    retval.push(BagError::UndefinedColour { colour: colour.to_string(), inside: Some(parent.to_string()) });
+                }
+            }
+        }
+        retval.sort_by_key(|error| error.to_string());
+        retval
+    }
+
+    /// Some run of colours that ends up inside itself, if the rules have one.
+    fn find_cycle(&self) -> Option<BagError> {
+        //Depth-first from every colour, with an explicit stack of (colour, next child) frames;
+        //`done` holds colours known to lead to no cycle.
+        let mut colours:Vec<&str> = self.children.keys().copied().collect();
+        colours.sort_unstable();
+        let mut done:HashSet<&str> = HashSet::new();
+        for colour in colours {
+            if done.contains(colour) {
+                continue;
+            }
+            let mut path:Vec<(&str,usize)> = vec![(colour,0)];
+            let mut on_path:HashSet<&str> = HashSet::from([colour]);
+            while let Some(frame) = path.last_mut() {
+                let Some((child,_)) = self.children(frame.0).get(frame.1).copied() else {
+                    on_path.remove(frame.0);
+                    done.insert(frame.0);
+                    path.pop();
+                    continue;
+                };
+                frame.1 += 1;
+                if on_path.contains(child) {
+                    let start = path.iter().position(|(outer,_)| *outer == child).expect("Colour is on the path.");
+                    let cycle = path[start..].iter().map(|(c,_)| c.to_string()).chain([child.to_string()]).collect();
+                    return Some(BagError::Cycle(cycle));
+                }
+                if !done.contains(child) {
+                    on_path.insert(child);
+                    path.push((child,0));
+                }
+            }
+        }
+        None
+    }
+
+    /// The shortest run of colours from `outer` to `inner` where each bag directly holds the
+    /// next, both ends included. With `outer` and `inner` the same, this is the shortest way a
+    /// bag ends up inside itself, if it can.
+    fn shortest_chain(&self, outer:&str, inner:&str) -> Option<Vec<&'a str>> {
+        let (&outer,_) = self.children.get_key_value(outer)?;
+        //Breadth-first from the bags directly inside `outer`, remembering which bag each colour
+        //was first reached from.
+        let mut reached_from:HashMap<&str,&str> = HashMap::new();
+        let mut queue:VecDeque<&str> = VecDeque::from([outer]);
+        while let Some(current) = queue.pop_front() {
+            for (child,_) in self.children(current) {
+                if *child == inner {
+                    let mut retval = vec![*child, current];
+                    let mut at = current;
+                    while at != outer {
+                        at = reached_from[at];
+                        retval.push(at);
+                    }
+                    retval.reverse();
+                    return Some(retval);
+                }
+                if *child != outer && !reached_from.contains_key(child) {
+                    reached_from.insert(child, current);
+                    queue.push_back(child);
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Which star's bags to pick out in a drawing of the rules.
+#[derive(Clone,Copy,PartialEq)]
+enum Highlight {
+    None,
+    /// Colours that can hold a shiny gold bag.
+    StarOne,
+    /// Colours a shiny gold bag ends up holding.
+    StarTwo,
+}
+
+/// Writes the rules as a Graphviz digraph, with an edge from each colour to every colour it
+/// directly holds, labelled with how many. Only colours in `keep` are drawn, if given.
+fn write_dot(graph:&BagGraph, keep:Option<&HashSet<&str>>, highlight:Highlight, out:&mut impl Write) -> io::Result<()> {
+    let marked = match highlight {
+        Highlight::None => HashSet::new(),
+        Highlight::StarOne => graph.ancestors("shiny gold"),
+        Highlight::StarTwo => graph.descendants("shiny gold"),
+    };
+    let mut colours:Vec<&str> = graph.children.keys().chain(graph.parents.keys()).copied()
+        .filter(|colour| keep.is_none_or(|keep| keep.contains(colour)))
+        .collect();
+    colours.sort_unstable();
+    colours.dedup();
+
+    writeln!(out,"digraph bags {{")?;
+    writeln!(out,"    node [shape=box, style=rounded];")?;
+    for colour in colours.iter() {
+        let style = if *colour == "shiny gold" && highlight != Highlight::None {
+            " [style=\"rounded,filled\", fillcolor=gold]"
+        } else if marked.contains(colour) {
+            " [style=\"rounded,filled\", fillcolor=lightblue]"
         } else {
-            let mut total = 1;
-            for (other, amount) in containing.iter() {
-                let subtotal = *amount * visit_bag(*other, &tree);
-                total += subtotal;
+            ""
+        };
+        writeln!(out,"    {}{style};",dot_string(colour))?;
+    }
+    for colour in colours.iter() {
+        for (child,amount) in graph.children(colour) {
+            if keep.is_none_or(|keep| keep.contains(child)) {
+                writeln!(out,"    {} -> {} [label=\"{amount}\"];",dot_string(colour),dot_string(child))?;
             }
-            total
         }
     }
+    writeln!(out,"}}")
+}
+
+fn dot_string(value:&str) -> String {
+    format!("\"{}\"",value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn setup(input_path:&str) -> Vec<BagRule> {
+    let str_input = fs::read_to_string(input_path).expect("Could not read file.");
+    let (retval,errors) = parse_rules(&str_input);
+    for error in errors {
+        eprintln!("{error}");
+    }
+    return retval;
+}
 
-    return (visit_bag("shiny gold", &tree)-1).to_string();
+fn star_one(graph:&BagGraph) -> String {
+    graph.ancestors("shiny gold").len().to_string()
 }
 
+fn star_two(graph:&BagGraph) -> String {
+    match graph.total_contained("shiny gold") {
+        Ok(total) => total.to_string(),
+        Err(e) => e.to_string(),
+    }
+}
+
+/// `day7-bags <input> <query> [colour] [colour]`: answers one question about the rules, where
+/// the query is one of `ancestors`, `descendants`, `parents`, `children`, `rule`, `total`, `chain`
+/// or `check`. `chain` takes two colours, and finds the shortest way to nest the second in the
+/// first; `check` takes none, and lists undefined colours and a bag that holds itself, if any.
+pub fn bags_command(args:&[String]) -> Result<(),String> {
+    let usage = "Usage: day7-bags <input> ancestors|descendants|parents|children|rule|total|chain|check [colour] [colour]";
+    let [input_path, query, colours @ ..] = args else {
+        return Err(usage.into());
+    };
+    let rules = load_rules(input_path)?;
+    let graph = BagGraph::new(&rules);
+    for colour in colours {
+        if !graph.children.contains_key(colour.as_str()) && !graph.parents.contains_key(colour.as_str()) {
+            return Err(format!("No rule mentions {colour} bags."));
+        }
+    }
+
+    let print_sorted = |colours:Vec<&str>| {
+        let mut colours = colours;
+        colours.sort_unstable();
+        println!("{} colour(s)",colours.len());
+        for colour in colours {
+            println!("  {colour}");
+        }
+    };
+    match (query.as_str(), colours) {
+        ("ancestors", [colour]) => print_sorted(graph.ancestors(colour).into_iter().collect()),
+        ("descendants", [colour]) => print_sorted(graph.descendants(colour).into_iter().collect()),
+        ("parents", [colour]) => print_sorted(graph.parents(colour).to_vec()),
+        ("children", [colour]) => {
+            for (child,amount) in graph.children(colour) {
+                println!("  {amount} {child}");
+            }
+        },
+        ("rule", [colour]) => match rules.iter().find(|rule| rule.color == *colour) {
+            Some(rule) => println!("{rule}"),
+            None => println!("There is no rule for {colour} bags."),
+        },
+        ("total", [colour]) => println!("{}",graph.total_contained(colour).map_err(|e| e.to_string())?),
+        ("chain", [outer,inner]) => match graph.shortest_chain(outer, inner) {
+            Some(chain) => println!("{}",chain.join(" > ")),
+            None => println!("{inner} bags do not fit in {outer} bags."),
+        },
+        ("check", []) => {
+            let problems:Vec<BagError> = graph.undefined_colours().into_iter().chain(graph.find_cycle()).collect();
+            if problems.is_empty() {
+                println!("All {} rules check out.",rules.len());
+            }
+            for problem in problems {
+                println!("{problem}");
+            }
+        },
+        _ => return Err(usage.into()),
+    }
+    Ok(())
+}
+
+/// `day7-dot <input> [ancestors|descendants <colour>] [--highlight one|two]`: writes the rules as
+/// a Graphviz digraph to stdout. A colour limits the graph to the bags that can hold it, or
+/// that it can hold; `--highlight` picks out the bags counted by one of the stars.
+pub fn dot_command(args:&[String]) -> Result<(),String> {
+    let usage = "Usage: day7-dot <input> [ancestors|descendants <colour>] [--highlight one|two]";
+    let mut args = args.to_vec();
+    let highlight = match args.iter().position(|arg| arg == "--highlight") {
+        None => Highlight::None,
+        Some(index) => {
+            let star = args.get(index+1).cloned().ok_or_else(|| usage.to_string())?;
+            args.drain(index..=index+1);
+            match star.as_str() {
+                "one" => Highlight::StarOne,
+                "two" => Highlight::StarTwo,
+                _ => return Err(format!("Unknown star <{star}>. {usage}")),
+            }
+        },
+    };
+    let (input_path, limit) = match &args[..] {
+        [input_path] => (input_path, None),
+        [input_path, direction, colour] => (input_path, Some((direction, colour))),
+        _ => return Err(usage.into()),
+    };
+    let rules = load_rules(input_path)?;
+    let graph = BagGraph::new(&rules);
+
+    let keep = match limit {
+        None => None,
+        Some((direction, colour)) => {
+            let mut keep = match direction.as_str() {
+                "ancestors" => graph.ancestors(colour),
+                "descendants" => graph.descendants(colour),
+                _ => return Err(format!("Unknown direction <{direction}>. {usage}")),
+            };
+            keep.insert(colour);
+            Some(keep)
+        },
+    };
+    write_dot(&graph, keep.as_ref(), highlight, &mut io::stdout().lock()).map_err(|e| format!("Could not write graph: {e}"))
+}
 
 pub fn run_day(input_path:&str) {
     let rules = setup(input_path);
-    let one = star_one(&rules);
-    let two = star_two(&rules);
+    let graph = BagGraph::new(&rules);
+    let one = star_one(&graph);
+    let two = star_two(&graph);
 
     println!("Day 7.\nStar one: {one}\nStar two: {two}");
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE_RULES:&str = "light red bags contain 1 bright white bag, 2 muted yellow bags.
+dark orange bags contain 3 bright white bags, 4 muted yellow bags.
+bright white bags contain 1 shiny gold bag.
+muted yellow bags contain 2 shiny gold bags, 9 faded blue bags.
+shiny gold bags contain 1 dark olive bag, 2 vibrant plum bags.
+dark olive bags contain 3 faded blue bags, 4 dotted black bags.
+vibrant plum bags contain 5 faded blue bags, 6 dotted black bags.
+faded blue bags contain no other bags.
+dotted black bags contain no other bags.";
+
+    #[test]
+    fn rules_round_trip() {
+        for line in EXAMPLE_RULES.lines() {
+            let rule = BagRule::from_str(line).unwrap_or_else(|e| panic!("<{line}> did not parse: {e}"));
+            assert_eq!(rule.to_string(), line);
+        }
+    }
+
+    #[test]
+    fn colours_may_contain_no() {
+        let line = "shiny no bags contain 2 no way bags, 1 big no bag.";
+        let rule = BagRule::from_str(line).unwrap_or_else(|e| panic!("<{line}> did not parse: {e}"));
+        assert_eq!(rule.color, "shiny no");
+        assert_eq!(rule.contains, vec![("no way".to_string(),2), ("big no".to_string(),1)]);
+        assert_eq!(rule.to_string(), line);
+    }
+
+    #[test]
+    fn errors_point_at_the_column() {
+        let error = |line:&str| BagRule::from_str(line).err().map(|e| e.column);
+        assert_eq!(error("a  b bags contain no other bags."), Some(3));
+        assert_eq!(error("a b bags contain 1 c bags."), Some(22));
+        assert_eq!(error("a b bags contain 2 c bags"), Some(26));
+        assert_eq!(error("a b bags contain no other bags"), Some(27));
+    }
+
+    #[test]
+    fn chains_need_at_least_one_step() {
+        let (rules,_) = parse_rules(EXAMPLE_RULES);
+        let graph = BagGraph::new(&rules);
+        assert_eq!(graph.shortest_chain("light red", "faded blue"), Some(vec!["light red", "muted yellow", "faded blue"]));
+        assert_eq!(graph.shortest_chain("shiny gold", "shiny gold"), None);
+        assert_eq!(graph.shortest_chain("faded blue", "light red"), None);
+
+        let (rules,_) = parse_rules("a b bags contain 1 a b bag.");
+        let graph = BagGraph::new(&rules);
+        assert_eq!(graph.shortest_chain("a b", "a b"), Some(vec!["a b", "a b"]));
+    }
+
+    #[test]
+    fn example_answers() {
+        let (rules,errors) = parse_rules(EXAMPLE_RULES);
+        assert!(errors.is_empty());
+        let graph = BagGraph::new(&rules);
+        assert_eq!(star_one(&graph), "4");
+        assert_eq!(star_two(&graph), "32");
+    }
+}
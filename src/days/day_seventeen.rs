@@ -2,6 +2,8 @@ use std::collections::HashSet;
 use std::ops::Add;
 use std::{isize, usize};
 
+use crate::common::life;
+
 
 
 #[derive(PartialEq, Eq, Clone, Copy, Hash)]
@@ -168,52 +170,16 @@ impl Iterator for CoordinateHyperNeighborIterator {
 const GEN_NEIGH_3D:[Coordinate;26] = make_3d_lookup();
 const GEN_NEIGH_4D:[Coordinate;80] = make_4d_lookup();
 
+fn conway_rule(alive:bool, neighbours:usize) -> bool {
+    neighbours == 3 || (alive && neighbours == 2)
+}
+
 fn gol_step(initial:HashSet<Coordinate>) -> HashSet<Coordinate> {
-    let mut retval:HashSet<Coordinate> = HashSet::new();
-
-    for live in initial.iter() {
-        for adjacent in live.neighbors_3d() {
-            let mut neighbor_count:u8 = 0;
-            for to_check in adjacent.neighbors_3d() {
-                if initial.contains(&to_check) {
-                    neighbor_count += 1;
-                }
-            }
-
-            if initial.contains(&adjacent) {
-                if neighbor_count == 2 || neighbor_count == 3 {
-                    retval.insert(adjacent);
-                }
-            } else if neighbor_count == 3 {
-                retval.insert(adjacent);
-            }
-        }
-    }
-    retval
+    life::step(&initial, Coordinate::neighbors_3d, conway_rule)
 }
 
 fn gol_4d_step(initial:HashSet<Coordinate>) -> HashSet<Coordinate> {
-    let mut retval:HashSet<Coordinate> = HashSet::new();
-
-    for live in initial.iter() {
-        for adjacent in live.neighbors_4d() {
-            let mut neighbor_count:u8 = 0;
-            for to_check in adjacent.neighbors_4d() {
-                if initial.contains(&to_check) {
-                    neighbor_count += 1;
-                }
-            }
-
-            if initial.contains(&adjacent) {
-                if neighbor_count == 2 || neighbor_count == 3 {
-                    retval.insert(adjacent);
-                }
-            } else if neighbor_count == 3 {
-                retval.insert(adjacent);
-            }
-        }
-    }
-    retval
+    life::step(&initial, Coordinate::neighbors_4d, conway_rule)
 }
 
 type State = Vec<Coordinate>;
@@ -1,72 +1,315 @@
 use std::fs;
-use std::collections::HashSet;
+use std::fmt;
+use std::io::{self, Write};
 use std::sync::atomic::{AtomicBool,Ordering};
 
+use crate::common::records::read_records;
+
 static VERBOSE: AtomicBool = AtomicBool::new(false);
 pub fn verbosity_set(verbose:bool) {
     VERBOSE.store(verbose, Ordering::Release);
 }
 
-fn setup(input_file:&str) -> Vec<Vec<Vec<char>>> { //groups, persons, answers.
-    let input_data = fs::read_to_string(input_file).expect("Could not read file.");
-    let mut retval:Vec<Vec<Vec<char>>> = Vec::new();
-    let mut group:Vec<Vec<char>> = Vec::new();
-    let verbose = VERBOSE.fetch_and(true, Ordering::Acquire);
-    for person in input_data.lines() {
-        if person == "" {
-            if verbose {
-                println!("Group {:?} parsed.",group);
+const QUESTIONS:usize = 26;
+
+/// The questions one or more people answered "yes" to, one bit per question `a` to `z`.
+#[derive(Clone,Copy,PartialEq,Eq,Default)]
+struct AnswerSet(u32);
+
+impl AnswerSet {
+    const ALL:AnswerSet = AnswerSet((1 << QUESTIONS) - 1);
+
+    fn parse(line:&str) -> Result<Self,String> {
+        let mut retval = AnswerSet::default();
+        for question in line.trim().chars() {
+            if !question.is_ascii_lowercase() {
+                return Err(format!("<{question}> is not a question; expected a to z."));
             }
-            retval.push(group);
-            group = Vec::new();
-        } else {
-            group.push(person.chars().collect());
+            retval.0 |= 1 << (question as u8 - b'a');
         }
+        Ok(retval)
     }
-    if verbose {
-        println!("Final group {:?} parsed.",group);
+
+    fn union(self, other:AnswerSet) -> AnswerSet {
+        AnswerSet(self.0 | other.0)
     }
-    retval.push(group);
 
-    return retval;
+    fn intersection(self, other:AnswerSet) -> AnswerSet {
+        AnswerSet(self.0 & other.0)
+    }
+
+    fn len(self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    fn contains(self, question:usize) -> bool {
+        self.0 & (1 << question) != 0
+    }
+
+    /// Indices (0 for `a`) of the questions in this set.
+    fn questions(self) -> impl Iterator<Item = usize> {
+        (0..QUESTIONS).filter(move |question| self.contains(*question))
+    }
+}
+
+impl fmt::Display for AnswerSet {
+    fn fmt(&self, f:&mut fmt::Formatter<'_>) -> fmt::Result {
+        for question in self.questions() {
+            write!(f, "{}", question_name(question))?;
+        }
+        Ok(())
+    }
+}
+
+fn question_name(question:usize) -> char {
+    (b'a' + question as u8) as char
 }
 
-fn star_one(input:&Vec<Vec<Vec<char>>>) -> String {
-    let mut retval = 0;
-    for group in input.iter() {
-        let mut answers:HashSet<char> = HashSet::new();
-        for person in group {
-            for answer in person {
-                answers.insert(*answer);
+struct Group {
+    /// 1-based line number of the group's first person in the input.
+    first_line:usize,
+    people:Vec<AnswerSet>,
+}
+
+impl Group {
+    /// Questions anyone in the group answered.
+    fn any(&self) -> AnswerSet {
+        self.people.iter().fold(AnswerSet::default(), |acc,person| acc.union(*person))
+    }
+
+    /// Questions everyone in the group answered.
+    fn all(&self) -> AnswerSet {
+        self.people.iter().fold(AnswerSet::ALL, |acc,person| acc.intersection(*person))
+    }
+
+    /// How many people in the group answered each question.
+    fn frequencies(&self) -> [usize;QUESTIONS] {
+        let mut retval = [0;QUESTIONS];
+        for person in self.people.iter() {
+            for question in person.questions() {
+                retval[question] += 1;
             }
         }
-        retval += answers.len();
+        retval
     }
-    retval.to_string()
+
+    /// Questions answered by at least `k` people in the group.
+    fn answered_by_at_least(&self, k:usize) -> AnswerSet {
+        let mut retval = AnswerSet::default();
+        for (question,count) in self.frequencies().iter().enumerate() {
+            if *count >= k {
+                retval.0 |= 1 << question;
+            }
+        }
+        retval
+    }
+}
+
+/// How many people answered each question, over all groups.
+fn frequencies(groups:&[Group]) -> [usize;QUESTIONS] {
+    let mut retval = [0;QUESTIONS];
+    for group in groups.iter() {
+        for (total,count) in retval.iter_mut().zip(group.frequencies()) {
+            *total += count;
+        }
+    }
+    retval
+}
+
+/// Splits the input into groups, naming the line of the first person whose answers are not
+/// all letters `a` to `z`.
+fn parse_groups(input_data:&str) -> Result<Vec<Group>,String> {
+    let mut retval:Vec<Group> = Vec::new();
+    for record in read_records(input_data) {
+        let people = record.numbered_lines()
+            .map(|(number,person)| AnswerSet::parse(person).map_err(|e| format!("Line {number}: {e}")))
+            .collect::<Result<Vec<AnswerSet>,String>>()?;
+        retval.push(Group { first_line: record.first_line, people });
+    }
+    Ok(retval)
+}
+
+fn setup(input_file:&str) -> Vec<Group> {
+    let input_data = fs::read_to_string(input_file).expect("Could not read file.");
+    let retval = parse_groups(&input_data).unwrap_or_else(|e| panic!("{e}"));
+    if VERBOSE.fetch_and(true, Ordering::Acquire) {
+        for group in retval.iter() {
+            let people:Vec<String> = group.people.iter().map(AnswerSet::to_string).collect();
+            println!("Group at line {} parsed: {:?}",group.first_line,people);
+        }
+    }
+
+    return retval;
+}
+
+/// The questions answered by the most and by the fewest people, leaving out questions nobody
+/// answered. Ties are all included.
+fn popularity(frequencies:&[usize;QUESTIONS]) -> (AnswerSet,AnswerSet) {
+    let answered = frequencies.iter().copied().filter(|count| *count > 0);
+    let (Some(most),Some(least)) = (answered.clone().max(),answered.min()) else {
+        return (AnswerSet::default(),AnswerSet::default());
+    };
+    let with_count = |wanted:usize| frequencies.iter().enumerate()
+        .filter(|(_,count)| **count == wanted)
+        .fold(AnswerSet::default(), |acc,(question,_)| acc.union(AnswerSet(1 << question)));
+    (with_count(most),with_count(least))
+}
+
+struct GroupStats {
+    first_line:usize,
+    size:usize,
+    any:AnswerSet,
+    all:AnswerSet,
+    most_popular:AnswerSet,
+    least_popular:AnswerSet,
 }
 
-fn star_two(input:&Vec<Vec<Vec<char>>>) -> String {
-    let mut retval = 0;
-    
-    let mut yesses:HashSet<char> = HashSet::new();
-    for group in input.iter() {
-        yesses.extend(group[0].iter());
-        if group.len() > 1 {
-            for answer in group[1..].iter() {
-                yesses.retain(|x| answer.contains(x));
+impl GroupStats {
+    /// Nobody agrees when no question was answered by everyone in the group.
+    fn nobody_agrees(&self) -> bool {
+        self.all.len() == 0
+    }
+}
+
+struct SurveyReport {
+    groups:Vec<GroupStats>,
+    frequencies:[usize;QUESTIONS],
+}
+
+impl SurveyReport {
+    fn build(groups:&[Group]) -> Self {
+        let stats = groups.iter().map(|group| {
+            let (most_popular,least_popular) = popularity(&group.frequencies());
+            GroupStats {
+                first_line: group.first_line,
+                size: group.people.len(),
+                any: group.any(),
+                all: group.all(),
+                most_popular,
+                least_popular,
             }
+        }).collect();
+        SurveyReport { groups: stats, frequencies: frequencies(groups) }
+    }
+
+    fn people(&self) -> usize {
+        self.groups.iter().map(|group| group.size).sum()
+    }
+
+    fn write_table(&self, out:&mut impl Write) -> io::Result<()> {
+        writeln!(out,"{:>6} {:>5} {:>4} {:>4}  {:<26}  {:<26}  agrees",
+            "line","size","any","all","most popular","least popular")?;
+        for group in self.groups.iter() {
+            writeln!(out,"{:>6} {:>5} {:>4} {:>4}  {:<26}  {:<26}  {}",
+                group.first_line,group.size,group.any.len(),group.all.len(),
+                group.most_popular.to_string(),group.least_popular.to_string(),
+                if group.nobody_agrees() { "no" } else { "yes" })?;
         }
-        retval += yesses.len();
-        yesses.clear();
+
+        let people = self.people();
+        let disagreeing = self.groups.iter().filter(|group| group.nobody_agrees()).count();
+        let (most_popular,least_popular) = popularity(&self.frequencies);
+        let unanswered = AnswerSet((0..QUESTIONS)
+            .filter(|question| self.frequencies[*question] == 0)
+            .fold(0, |acc,question| acc | 1 << question));
+        writeln!(out)?;
+        writeln!(out,"{} groups, {people} people",self.groups.len())?;
+        if let (Some(smallest),Some(largest)) = (self.groups.iter().map(|group| group.size).min(),self.groups.iter().map(|group| group.size).max()) {
+            writeln!(out,"group size: smallest {smallest}, largest {largest}, mean {:.2}",people as f64 / self.groups.len() as f64)?;
+        }
+        writeln!(out,"most popular: {most_popular}")?;
+        writeln!(out,"least popular: {least_popular}")?;
+        writeln!(out,"never answered: {unanswered}")?;
+        writeln!(out,"groups where nobody agrees: {disagreeing}")?;
+        writeln!(out,"answers per question:")?;
+        for (question,count) in self.frequencies.iter().enumerate() {
+            writeln!(out,"  {}: {count}",question_name(question))?;
+        }
+        Ok(())
+    }
+
+    /// One row per group, then a blank line and a second table of statistics over every group.
+    fn write_csv(&self, out:&mut impl Write) -> io::Result<()> {
+        writeln!(out,"first_line,size,any,all,most_popular,least_popular,nobody_agrees")?;
+        for group in self.groups.iter() {
+            writeln!(out,"{},{},{},{},{},{},{}",group.first_line,group.size,group.any.len(),group.all.len(),
+                group.most_popular,group.least_popular,group.nobody_agrees())?;
+        }
+
+        let (most_popular,least_popular) = popularity(&self.frequencies);
+        let any:usize = self.groups.iter().map(|group| group.any.len()).sum();
+        let all:usize = self.groups.iter().map(|group| group.all.len()).sum();
+        let disagreeing = self.groups.iter().filter(|group| group.nobody_agrees()).count();
+        writeln!(out)?;
+        writeln!(out,"statistic,key,value")?;
+        writeln!(out,"groups,,{}",self.groups.len())?;
+        writeln!(out,"people,,{}",self.people())?;
+        writeln!(out,"any,,{any}")?;
+        writeln!(out,"all,,{all}")?;
+        writeln!(out,"most_popular,,{most_popular}")?;
+        writeln!(out,"least_popular,,{least_popular}")?;
+        writeln!(out,"nobody_agrees,,{disagreeing}")?;
+        for (question,count) in self.frequencies.iter().enumerate() {
+            writeln!(out,"answers,{},{count}",question_name(question))?;
+        }
+        Ok(())
     }
+}
 
+fn star_one(input:&[Group]) -> String {
+    let retval:usize = input.iter().map(|group| group.any().len()).sum();
     retval.to_string()
 }
 
+fn star_two(input:&[Group]) -> String {
+    let retval:usize = input.iter().map(|group| group.all().len()).sum();
+    retval.to_string()
+}
+
+/// `day6-answers <input> <k>`: per group, the questions answered by at least `k` people, then
+/// how many people answered each question over all groups.
+pub fn answers_command(args:&[String]) -> Result<(),String> {
+    let [input_path,k] = args else {
+        return Err("Usage: day6-answers <input> <k>".into());
+    };
+    let k:usize = k.parse().map_err(|_| format!("<{k}> is not a number of people."))?;
+    let input_data = fs::read_to_string(input_path).map_err(|e| format!("Could not read {input_path}: {e}"))?;
+    let groups = parse_groups(&input_data)?;
+
+    for group in groups.iter() {
+        let answered = group.answered_by_at_least(k);
+        println!("Group at line {} ({} people): {} question(s) {}",group.first_line,group.people.len(),answered.len(),answered);
+    }
+    println!("Answers per question:");
+    for (question,count) in frequencies(&groups).iter().enumerate() {
+        println!("  {}: {count}",question_name(question));
+    }
+    Ok(())
+}
+
+/// `day6-stats <input> [table|csv]`: statistics per group and over all groups, as a table by
+/// default.
+pub fn stats_command(args:&[String]) -> Result<(),String> {
+    let usage = "Usage: day6-stats <input> [table|csv]";
+    let (input_path,format) = match args {
+        [input_path] => (input_path,"table"),
+        [input_path,format] => (input_path,format.as_str()),
+        _ => return Err(usage.into()),
+    };
+    let input_data = fs::read_to_string(input_path).map_err(|e| format!("Could not read {input_path}: {e}"))?;
+    let report = SurveyReport::build(&parse_groups(&input_data)?);
+    let mut out = io::stdout().lock();
+    match format {
+        "table" => report.write_table(&mut out),
+        "csv" => report.write_csv(&mut out),
+        _ => return Err(format!("Unknown format <{format}>. {usage}")),
+    }.map_err(|e| format!("Could not write report: {e}"))
+}
+
 pub fn run_day(input_file:&str) {
     let parsed_data = setup(input_file);
     let one = star_one(&parsed_data);
     let two = star_two(&parsed_data);
 
     println!("Day 6.\nStar one: {one}\nStar two: {two}");
-}
\ No newline at end of file
+}
@@ -1,61 +1,353 @@
-use std::fs;
-use std::collections::HashSet;
+use std::fs::{self, File};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, BufWriter, Write};
 
-#[derive(PartialEq,Eq,Hash)]
+#[derive(PartialEq,Eq,Hash,Clone,Copy)]
 struct TreeLoc {
     x:usize,
     y:usize,
 }
 
+/// What a path does when it runs past the bottom row.
+#[derive(Clone,Copy)]
+enum VerticalEdge {
+    /// Stop; the sled has reached the airport.
+    Stop,
+    /// Carry on from the top row, until the path gets back to where it started.
+    Wrap,
+}
+
+/// A slope map. Every square that is not open (`.`) holds an obstacle, keyed by its symbol.
+struct SlopeMap {
+    obstacles:HashMap<TreeLoc,char>,
+    width:usize,
+    height:usize,
+}
+
+impl SlopeMap {
+    /// The obstacle at this square, with the pattern repeating to the right.
+    fn obstacle_at(&self, x:usize, y:usize) -> Option<char> {
+        self.obstacles.get(&TreeLoc { x: x % self.width, y: y % self.height }).copied()
+    }
+
+    /// Squares visited along a slope, starting at the top left. Unless the path wraps
+    /// vertically, `x` keeps counting past the right edge of the pattern.
+    fn path(&self, h_slope:usize, v_slope:usize, edge:VerticalEdge) -> Vec<TreeLoc> {
+        match edge {
+            VerticalEdge::Stop => (0..self.height)
+                .step_by(v_slope)
+                .enumerate()
+                .map(|(steps,y)| TreeLoc { x: steps * h_slope, y })
+                .collect(),
+            VerticalEdge::Wrap => {
+                //Each step is a bijection on the squares, so the path has to come back to the start.
+                let mut retval = Vec::new();
+                let mut current = TreeLoc { x: 0, y: 0 };
+                loop {
+                    retval.push(current);
+                    current = TreeLoc {
+                        x: (current.x + h_slope) % self.width,
+                        y: (current.y + v_slope) % self.height,
+                    };
+                    if current.x == 0 && current.y == 0 {
+                        break;
+                    }
+                }
+                retval
+            },
+        }
+    }
+
+    /// Obstacles hit along a slope, per kind of obstacle.
+    fn hits_by_kind(&self, h_slope:usize, v_slope:usize, edge:VerticalEdge) -> BTreeMap<char,u32> {
+        let mut retval = BTreeMap::new();
+        for square in self.path(h_slope, v_slope, edge) {
+            if let Some(kind) = self.obstacle_at(square.x, square.y) {
+                *retval.entry(kind).or_insert(0) += 1;
+            }
+        }
+        retval
+    }
+}
 
-fn setup(input_path:&str) -> (HashSet<TreeLoc>,usize,usize) {
-    let mut retval:HashSet<TreeLoc> = HashSet::new();
-    let mut height:usize = 0;
-    let mut width:usize = 0;
-    let input_contents = fs::read_to_string(input_path).expect("Could not open input file.");
-    for (y,line) in input_contents.lines().enumerate() {
-        for (x,letter) in line.chars().enumerate() {
-            if letter == '#' {
-                retval.insert(TreeLoc { x: x, y: y });
+fn parse_map(input:&str) -> Result<SlopeMap,String> {
+    let mut rows:Vec<&str> = input.lines().collect();
+    while rows.last().is_some_and(|row| row.trim().is_empty()) {
+        rows.pop();
+    }
+    let width = rows.first().map_or(0, |row| row.chars().count());
+    if width == 0 {
+        return Err("The map is empty.".into());
+    }
+
+    let mut obstacles:HashMap<TreeLoc,char> = HashMap::new();
+    for (y,row) in rows.iter().enumerate() {
+        let row_width = row.chars().count();
+        if row_width != width {
+            return Err(format!("Row {} is {row_width} squares wide, expected {width} like the first row.",y+1));
+        }
+        for (x,letter) in row.chars().enumerate() {
+            if letter.is_whitespace() {
+                return Err(format!("Row {}, column {}: unexpected whitespace.",y+1,x+1));
+            }
+            if letter != '.' {
+                obstacles.insert(TreeLoc { x, y }, letter);
             }
-            width = line.len();
         }
-        height = y;
     }
-    return (retval,height,width);
+    Ok(SlopeMap { obstacles, width, height: rows.len() })
 }
 
-fn sled(trees:&HashSet<TreeLoc>,height:usize,width:usize,h_slope:usize,v_slope:usize) -> u32 {
-    let mut tree_count: u32 = 0;
-    for(steps,y) in (0..=height).step_by(v_slope).enumerate() {
-        let x = (steps * h_slope) % width;
-        if trees.contains(&TreeLoc { x: x, y: y }) {
-            //println!("Hit a tree {x},{y}");
-            tree_count += 1;
+fn load_map(input_path:&str) -> Result<SlopeMap,String> {
+    let input_contents = fs::read_to_string(input_path).map_err(|e| format!("Could not open {input_path}: {e}"))?;
+    parse_map(&input_contents)
+}
+
+fn setup(input_path:&str) -> SlopeMap {
+    load_map(input_path).unwrap_or_else(|e| panic!("Malformed map: {e}"))
+}
+
+fn sled(map:&SlopeMap,h_slope:usize,v_slope:usize,edge:VerticalEdge) -> u32 {
+    map.hits_by_kind(h_slope, v_slope, edge).values().sum()
+}
+
+/// Pulls a `--wrap` flag out of the arguments.
+fn vertical_edge(args:&[String]) -> (VerticalEdge,Vec<String>) {
+    let rest:Vec<String> = args.iter().filter(|arg| *arg != "--wrap").cloned().collect();
+    let edge = if rest.len() < args.len() { VerticalEdge::Wrap } else { VerticalEdge::Stop };
+    (edge,rest)
+}
+
+/// Slopes as (right, down) steps.
+type Slope = (usize,usize);
+
+const STAR_ONE_SLOPES:[Slope;1] = [(3,1)];
+const STAR_TWO_SLOPES:[Slope;5] = [(1,1),(3,1),(5,1),(7,1),(1,2)];
+
+fn parse_slope(slope:&str) -> Result<Slope,String> {
+    let (right, down) = slope.split_once(',').ok_or(format!("Expected a slope as right,down; found <{slope}>."))?;
+    let right = right.trim().parse::<usize>().map_err(|_| format!("Could not parse right step <{right}>."))?;
+    let down = down.trim().parse::<usize>().map_err(|_| format!("Could not parse down step <{down}>."))?;
+    if down == 0 {
+        return Err(format!("Slope <{slope}> never reaches the bottom."));
+    }
+    Ok((right,down))
+}
+
+fn product_of_slopes(map:&SlopeMap,slopes:&[Slope],edge:VerticalEdge) -> u64 {
+    slopes.iter()
+        .map(|(v_x,v_y)| u64::from(sled(map,*v_x,*v_y,edge)))
+        .product()
+}
+
+/// Every slope with a right step up to `max_right` and a down step from 1 up to `max_down`,
+/// with the obstacles hit along it; safest first, ties broken on the slope.
+fn rank_slopes(map:&SlopeMap,max_right:usize,max_down:usize,edge:VerticalEdge) -> Vec<(Slope,u32)> {
+    let mut retval:Vec<(Slope,u32)> = Vec::new();
+    for v_x in 0..=max_right {
+        for v_y in 1..=max_down {
+            retval.push(((v_x,v_y),sled(map,v_x,v_y,edge)));
         }
     }
-    tree_count
+    retval.sort_by_key(|(slope,hits)| (*hits,*slope));
+    retval
 }
 
-fn first_star(trees:&HashSet<TreeLoc>,height:usize,width:usize) -> String {
-    sled(trees,height,width,3,1).to_string()
+#[derive(Clone,Copy,PartialEq)]
+enum MapCell {
+    Open,
+    Obstacle(char),
+    /// Open square the sled passed over.
+    Passed,
+    /// Obstacle the sled hit.
+    Hit,
 }
 
-fn second_star(trees:&HashSet<TreeLoc>,height:usize,width:usize) -> String {
-    let mut retval: u64 = 1;
-    let speeds = [(1,1),(3,1),(5,1),(7,1),(1,2)];
-    for (v_x,v_y) in speeds {
-        let hits = u64::from(sled(trees,height,width,v_x,v_y));
-        //println!("Hit {hits} trees, going {v_x} right and {v_y} down.");
-        retval *= hits;
+impl MapCell {
+    fn symbol(&self) -> char {
+        match self {
+            MapCell::Open => '.',
+            MapCell::Obstacle(kind) => *kind,
+            MapCell::Passed => 'O',
+            MapCell::Hit => 'X',
+        }
+    }
+
+    fn colour(&self) -> [u8;3] {
+        match self {
+            MapCell::Open => [0xf4,0xf4,0xf4],
+            MapCell::Obstacle(_) => [0x2e,0x7d,0x32],
+            MapCell::Passed => [0x15,0x65,0xc0],
+            MapCell::Hit => [0xd3,0x2f,0x2f],
+        }
+    }
+}
+
+/// The map along a slope, with the pattern repeated to the right as far as the path goes.
+/// Rows are indexed first.
+fn trace_path(map:&SlopeMap,h_slope:usize,v_slope:usize) -> Vec<Vec<MapCell>> {
+    let path = map.path(h_slope, v_slope, VerticalEdge::Stop);
+    let last_x = path.last().map_or(0, |square| square.x);
+    let repeats = last_x / map.width + 1;
+    let mut retval:Vec<Vec<MapCell>> = (0..map.height)
+        .map(|y| (0..repeats*map.width)
+            .map(|x| match map.obstacle_at(x, y) {
+                Some(kind) => MapCell::Obstacle(kind),
+                None => MapCell::Open,
+            })
+            .collect())
+        .collect();
+    for square in path {
+        let cell = &mut retval[square.y][square.x];
+        *cell = match cell {
+            MapCell::Obstacle(_) => MapCell::Hit,
+            _ => MapCell::Passed,
+        };
+    }
+    retval
+}
+
+fn render_text(grid:&[Vec<MapCell>]) -> String {
+    let mut retval = String::new();
+    for row in grid {
+        retval.extend(row.iter().map(MapCell::symbol));
+        retval.push('\n');
+    }
+    retval
+}
+
+/// Binary PPM, with every cell drawn as a `scale` by `scale` square.
+fn write_ppm(grid:&[Vec<MapCell>], scale:usize, out:&mut impl Write) -> io::Result<()> {
+    let width = grid.first().map_or(0, Vec::len);
+    write!(out,"P6\n{} {}\n255\n",width * scale,grid.len() * scale)?;
+    for row in grid {
+        let mut line:Vec<u8> = Vec::with_capacity(width * scale * 3);
+        for cell in row {
+            for _ in 0..scale {
+                line.extend_from_slice(&cell.colour());
+            }
+        }
+        for _ in 0..scale {
+            out.write_all(&line)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_svg(grid:&[Vec<MapCell>], scale:usize, out:&mut impl Write) -> io::Result<()> {
+    let width = grid.first().map_or(0, Vec::len);
+    writeln!(out,"<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">",width * scale,grid.len() * scale)?;
+    writeln!(out,"<rect width=\"100%\" height=\"100%\" fill=\"#f4f4f4\"/>")?;
+    for (y,row) in grid.iter().enumerate() {
+        for (x,cell) in row.iter().enumerate() {
+            if *cell == MapCell::Open {
+                continue;
+            }
+            let [r,g,b] = cell.colour();
+            writeln!(out,"<rect x=\"{}\" y=\"{}\" width=\"{scale}\" height=\"{scale}\" fill=\"#{r:02x}{g:02x}{b:02x}\"/>",x * scale,y * scale)?;
+        }
+    }
+    writeln!(out,"</svg>")
+}
+
+fn first_star(map:&SlopeMap) -> String {
+    product_of_slopes(map,&STAR_ONE_SLOPES,VerticalEdge::Stop).to_string()
+}
+
+fn second_star(map:&SlopeMap) -> String {
+    product_of_slopes(map,&STAR_TWO_SLOPES,VerticalEdge::Stop).to_string()
+}
+
+/// `day3-slopes <input> [--wrap] <right,down>...`: obstacles hit on each slope, and their product.
+/// With `--wrap`, paths continue from the top until they get back to the start.
+pub fn slopes_command(args:&[String]) -> Result<(),String> {
+    let usage = "Usage: day3-slopes <input> [--wrap] <right,down>...";
+    let (edge,args) = vertical_edge(args);
+    let [input_path, slopes @ ..] = &args[..] else {
+        return Err(usage.into());
+    };
+    if slopes.is_empty() {
+        return Err(usage.into());
+    }
+    let slopes = slopes.iter().map(|slope| parse_slope(slope)).collect::<Result<Vec<Slope>,String>>()?;
+    let map = load_map(input_path)?;
+    for (v_x,v_y) in slopes.iter() {
+        let by_kind:Vec<String> = map.hits_by_kind(*v_x,*v_y,edge)
+            .iter()
+            .map(|(kind,hits)| format!("{kind}: {hits}"))
+            .collect();
+        println!("Right {v_x}, down {v_y}: {} obstacles ({}).",sled(&map,*v_x,*v_y,edge),by_kind.join(", "));
+    }
+    println!("Product: {}",product_of_slopes(&map,&slopes,edge));
+    Ok(())
+}
+
+/// `day3-render <input> <right,down> [ppm|svg <output>]`: prints the map with the path marked,
+/// or writes it as an image.
+pub fn render_command(args:&[String]) -> Result<(),String> {
+    let usage = "Usage: day3-render <input> <right,down> [ppm|svg <output>]";
+    let (input_path, slope, image) = match args {
+        [input_path, slope] => (input_path, slope, None),
+        [input_path, slope, format, output] => (input_path, slope, Some((format, output))),
+        _ => return Err(usage.into()),
+    };
+    let (v_x,v_y) = parse_slope(slope)?;
+    let map = load_map(input_path)?;
+    let grid = trace_path(&map,v_x,v_y);
+
+    let Some((format, output)) = image else {
+        print!("{}",render_text(&grid));
+        return Ok(());
+    };
+    //Check the format before creating the file, so a typo leaves an existing file alone.
+    let ppm = match format.as_str() {
+        "ppm" => true,
+        "svg" => false,
+        _ => return Err(format!("Unknown image format <{format}>. {usage}")),
+    };
+    let file = File::create(output).map_err(|e| format!("Could not create {output}: {e}"))?;
+    let mut out = BufWriter::new(file);
+    if ppm {
+        write_ppm(&grid, 4, &mut out)
+    } else {
+        write_svg(&grid, 8, &mut out)
+    }.and_then(|_| out.flush()).map_err(|e| format!("Could not write {output}: {e}"))
+}
+
+/// `day3-rank <input> [--wrap] <max_right> <max_down> [count]`: the safest and the worst slopes
+/// within the bounds, `count` of each (5 by default).
+pub fn rank_command(args:&[String]) -> Result<(),String> {
+    let usage = "Usage: day3-rank <input> [--wrap] <max_right> <max_down> [count]";
+    let (edge,args) = vertical_edge(args);
+    let (input_path, max_right, max_down, count) = match &args[..] {
+        [input_path, max_right, max_down] => (input_path, max_right, max_down, "5"),
+        [input_path, max_right, max_down, count] => (input_path, max_right, max_down, count.as_str()),
+        _ => return Err(usage.into()),
+    };
+    let max_right = max_right.parse::<usize>().map_err(|_| format!("Could not parse <{max_right}>. {usage}"))?;
+    let max_down = max_down.parse::<usize>().map_err(|_| format!("Could not parse <{max_down}>. {usage}"))?;
+    let count = count.parse::<usize>().map_err(|_| format!("Could not parse <{count}>. {usage}"))?;
+    if max_down == 0 {
+        return Err("max_down has to be at least 1.".into());
+    }
+
+    let map = load_map(input_path)?;
+    let ranking = rank_slopes(&map,max_right,max_down,edge);
+    println!("Safest slopes:");
+    for ((v_x,v_y),hits) in ranking.iter().take(count) {
+        println!("  right {v_x}, down {v_y}: {hits} obstacles");
+    }
+    println!("Worst slopes:");
+    for ((v_x,v_y),hits) in ranking.iter().rev().take(count) {
+        println!("  right {v_x}, down {v_y}: {hits} obstacles");
     }
-    retval.to_string()
+    Ok(())
 }
 
 pub fn run_day(input_path:&str) {
-    let (input,height,width) = setup(input_path);
-    let first = first_star(&input,height,width);
-    let second = second_star(&input,height,width);
+    let map = setup(input_path);
+    let first = first_star(&map);
+    let second = second_star(&map);
 
     println!("Day 3.\nStar one: {first}\nStar two: {second}")
 }
\ No newline at end of file
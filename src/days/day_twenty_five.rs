@@ -0,0 +1,50 @@
+use crate::common::number_theory::{discrete_log, mod_pow};
+
+const MODULUS:u64 = 20201227;
+const SUBJECT:u64 = 7;
+
+/// Public keys of the card and the door, in that order.
+type State = (u64,u64);
+
+fn setup(input_path:&str) -> State {
+    let input = std::fs::read_to_string(input_path).expect("Could not open input file.");
+    let mut keys = input.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.trim().parse::<u64>().expect("Could not parse public key."));
+    let card = keys.next().expect("Input not large enough.");
+    let door = keys.next().expect("Input not large enough.");
+    (card,door)
+}
+
+/// Finds the loop size the way the handshake describes it: keep transforming the subject
+/// number until the public key comes out.
+fn brute_force_loop_size(public_key:u64) -> u64 {
+    assert!(public_key > 0 && public_key < MODULUS, "Public key {public_key} can never be produced.");
+    let mut value = 1;
+    let mut loop_size = 0;
+    while value != public_key {
+        value = (value * SUBJECT) % MODULUS;
+        loop_size += 1;
+    }
+    loop_size
+}
+
+fn star_one(initial_state:&State) -> String {
+    let (card, door) = *initial_state;
+    let loop_size = brute_force_loop_size(card);
+    format!("{}",mod_pow(door, loop_size, MODULUS))
+}
+
+fn star_two(initial_state:&State) -> String {
+    //Day 25 only has the one puzzle; derive the same key again, the fast way.
+    let (card, door) = *initial_state;
+    let loop_size = discrete_log(SUBJECT, card, MODULUS).expect("Public key can never be produced.");
+    format!("{}",mod_pow(door, loop_size, MODULUS))
+}
+
+pub fn run_day(input_path:&str) {
+    let initial_state = setup(input_path);
+    let one = star_one(&initial_state);
+    let two = star_two(&initial_state);
+    println!("Day 25.\nStar one: {one}\nStar two: {two}");
+}
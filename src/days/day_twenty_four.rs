@@ -0,0 +1,55 @@
+use std::collections::HashSet;
+
+use crate::common::hex::{HexCoord, HexDirection};
+use crate::common::life;
+
+type State = Vec<Vec<HexDirection>>;
+
+fn setup(input_path:&str) -> State {
+    std::fs::read_to_string(input_path)
+        .expect("Could not open input file.")
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| HexDirection::parse_path(line).expect("Malformed tile path."))
+        .collect()
+}
+
+/// Walks every path from the reference tile, flipping the tile it ends on.
+/// Returns the tiles that end up black side up.
+fn flip_tiles(paths:&State) -> HashSet<HexCoord> {
+    let mut black:HashSet<HexCoord> = HashSet::new();
+    for path in paths.iter() {
+        let tile = path.iter().fold(HexCoord::default(), |acc, step| acc.neighbour(*step));
+        if !black.remove(&tile) {
+            black.insert(tile);
+        }
+    }
+    black
+}
+
+fn tile_rule(black:bool, neighbours:usize) -> bool {
+    if black {
+        neighbours == 1 || neighbours == 2
+    } else {
+        neighbours == 2
+    }
+}
+
+fn star_one(initial_state:&State) -> String {
+    format!("{}",flip_tiles(initial_state).len())
+}
+
+fn star_two(initial_state:&State) -> String {
+    let mut floor = flip_tiles(initial_state);
+    for _ in 0..100 {
+        floor = life::step(&floor, HexCoord::neighbours, tile_rule);
+    }
+    format!("{}",floor.len())
+}
+
+pub fn run_day(input_path:&str) {
+    let initial_state = setup(input_path);
+    let one = star_one(&initial_state);
+    let two = star_two(&initial_state);
+    println!("Day 24.\nStar one: {one}\nStar two: {two}");
+}
@@ -0,0 +1,107 @@
+/// Ring of cups, stored as a successor list: `next[label]` is the label of the cup
+/// clockwise of the cup with that label. Index 0 is unused, so labels index directly.
+struct CupRing {
+    next:Vec<u32>,
+    current:u32,
+}
+
+impl CupRing {
+    /// Builds a ring from the given labels, followed by every label from
+    /// `initial.len()+1` up to and including `cup_count`. Panics on fewer than four cups.
+    fn new(initial:&[u32], cup_count:usize) -> Self {
+        assert!(!initial.is_empty(), "Need at least one cup.");
+        assert!(cup_count >= initial.len(), "Cup count smaller than the starting cups.");
+        //Three cups get picked up each move, so the destination needs a fourth.
+        assert!(cup_count >= 4, "Need at least four cups to play, got {cup_count}.");
+        let mut seen = vec![false;initial.len()+1];
+        for label in initial {
+            let label = *label as usize;
+            assert!((1..=initial.len()).contains(&label) && !seen[label], "Starting cups must be labelled 1 to {}.",initial.len());
+            seen[label] = true;
+        }
+
+        let mut next = vec![0u32;cup_count+1];
+        let first = initial[0];
+        let mut previous = first;
+        for label in initial[1..].iter().copied().chain(initial.len() as u32 + 1..=cup_count as u32) {
+            next[previous as usize] = label;
+            previous = label;
+        }
+        next[previous as usize] = first;
+
+        CupRing { next, current: first }
+    }
+
+    fn play_move(&mut self) {
+        let highest = (self.next.len() - 1) as u32;
+        let a = self.next[self.current as usize];
+        let b = self.next[a as usize];
+        let c = self.next[b as usize];
+
+        let mut destination = self.current;
+        loop {
+            destination = if destination == 1 { highest } else { destination - 1 };
+            if destination != a && destination != b && destination != c {
+                break;
+            }
+        }
+
+        //Lift a..c out of the ring, then splice them back in after the destination.
+        self.next[self.current as usize] = self.next[c as usize];
+        self.next[c as usize] = self.next[destination as usize];
+        self.next[destination as usize] = a;
+        self.current = self.next[self.current as usize];
+    }
+
+    /// Labels of all other cups, going clockwise from the cup labelled `label`.
+    fn after(&self, label:u32) -> impl Iterator<Item = u32> + '_ {
+        let mut cursor = label;
+        std::iter::from_fn(move || {
+            cursor = self.next[cursor as usize];
+            if cursor == label {
+                None
+            } else {
+                Some(cursor)
+            }
+        })
+    }
+}
+
+/// Plays `moves` rounds of the game on a ring of `cup_count` cups, the first of which
+/// are labelled as in `initial`.
+fn play(initial:&[u32], cup_count:usize, moves:usize) -> CupRing {
+    let mut ring = CupRing::new(initial, cup_count);
+    for _ in 0..moves {
+        ring.play_move();
+    }
+    ring
+}
+
+type State = Vec<u32>;
+
+fn setup(input_path:&str) -> State {
+    std::fs::read_to_string(input_path)
+        .expect("Could not open input file.")
+        .trim()
+        .chars()
+        .map(|c| c.to_digit(10).expect("Cup labels should be digits."))
+        .collect()
+}
+
+fn star_one(initial_state:&State) -> String {
+    let ring = play(initial_state, initial_state.len(), 100);
+    ring.after(1).map(|label| label.to_string()).collect()
+}
+
+fn star_two(initial_state:&State) -> String {
+    let ring = play(initial_state, 1_000_000, 10_000_000);
+    let product:u64 = ring.after(1).take(2).map(u64::from).product();
+    format!("{product}")
+}
+
+pub fn run_day(input_path:&str) {
+    let initial_state = setup(input_path);
+    let one = star_one(&initial_state);
+    let two = star_two(&initial_state);
+    println!("Day 23.\nStar one: {one}\nStar two: {two}");
+}
@@ -1,5 +1,12 @@
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::convert::TryFrom;
+use std::fmt::Display;
+use std::io::{self, Write};
+
+use regex::Regex;
+
+use crate::common::parse::ParseError;
 
 struct PasswordLine{
     min:usize,
@@ -8,72 +15,828 @@ struct PasswordLine{
     password:String,
 }
 
+/// Walks through a line one character at a time, keeping track of the column for errors.
+struct LineCursor<'a> {
+    chars:std::iter::Peekable<std::str::Chars<'a>>,
+    column:usize,
+}
+
+impl<'a> LineCursor<'a> {
+    fn new(line:&'a str) -> Self {
+        LineCursor { chars: line.chars().peekable(), column: 1 }
+    }
+
+    fn error(&self, message:String) -> ParseError {
+        ParseError { column: self.column, message }
+    }
+
+    fn describe(found:Option<char>) -> String {
+        match found {
+            Some(c) => format!("'{c}'"),
+            None => "the end of the line".into(),
+        }
+    }
+
+    fn number(&mut self, what:&str) -> Result<usize,ParseError> {
+        let start = self.column;
+        let mut digits = String::new();
+        while let Some(c) = self.chars.next_if(char::is_ascii_digit) {
+            digits.push(c);
+            self.column += 1;
+        }
+        if digits.is_empty() {
+            let found = self.chars.peek().copied();
+            return Err(self.error(format!("expected {what}, found {}",Self::describe(found))));
+        }
+        digits.parse::<usize>().map_err(|_| ParseError { column: start, message: format!("{what} {digits} is too large") })
+    }
+
+    fn expect(&mut self, expected:char) -> Result<(),ParseError> {
+        match self.chars.peek().copied() {
+            Some(c) if c == expected => {
+                self.chars.next();
+                self.column += 1;
+                Ok(())
+            },
+            found => Err(self.error(format!("expected '{expected}', found {}",Self::describe(found)))),
+        }
+    }
+
+    fn character(&mut self) -> Result<char,ParseError> {
+        match self.chars.peek().copied() {
+            Some(c) if !c.is_whitespace() && c != ':' => {
+                self.chars.next();
+                self.column += 1;
+                Ok(c)
+            },
+            found => Err(self.error(format!("expected the policy character, found {}",Self::describe(found)))),
+        }
+    }
+
+    fn password(&mut self) -> Result<String,ParseError> {
+        if self.chars.peek().is_none() {
+            return Err(self.error("expected a password, found the end of the line".into()));
+        }
+        let mut retval = String::new();
+        while let Some(c) = self.chars.next() {
+            if c.is_whitespace() {
+                return Err(self.error("passwords cannot contain whitespace".into()));
+            }
+            retval.push(c);
+            self.column += 1;
+        }
+        Ok(retval)
+    }
+}
+
 impl TryFrom<&str> for PasswordLine {
-    type Error = &'static str;
+    type Error = ParseError;
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        //check format
-        if value.matches('-').count() != 1 {
-            return Err("format mismatch; exactly 1 - is expected.");
+        //format: <min>-<max> <character>: <password>
+        let mut cursor = LineCursor::new(value.trim_end());
+        let min = cursor.number("a minimum")?;
+        cursor.expect('-')?;
+        let max = cursor.number("a maximum")?;
+        cursor.expect(' ')?;
+        let character = cursor.character()?;
+        cursor.expect(':')?;
+        cursor.expect(' ')?;
+        let password = cursor.password()?;
+        Ok(PasswordLine { min, max, character, password })
+    }
+}
+
+impl Display for PasswordLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f,"{}-{} {}: {}",self.min,self.max,self.character,self.password)
+    }
+}
+
+/// Why a policy could not be checked against a password line at all.
+struct PolicyError {
+    policy:String,
+    message:String,
+}
+
+impl Display for PolicyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f,"{}: {}",self.policy,self.message)
+    }
+}
+
+/// Why a password line does not satisfy a policy.
+struct PolicyFailure {
+    policy:String,
+    reason:String,
+}
+
+impl Display for PolicyFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f,"{}: {}",self.policy,self.reason)
+    }
+}
+
+trait PasswordPolicy {
+    fn name(&self) -> String;
+
+    /// Every reason the line fails this policy; empty if it passes.
+    fn failures(&self, line:&PasswordLine) -> Result<Vec<PolicyFailure>,PolicyError>;
+
+    fn passes(&self, line:&PasswordLine) -> Result<bool,PolicyError> {
+        Ok(self.failures(line)?.is_empty())
+    }
+
+    fn fail(&self, reason:String) -> Result<Vec<PolicyFailure>,PolicyError> {
+        Ok(vec![PolicyFailure { policy: self.name(), reason }])
+    }
+}
+
+/// The sled rental policy: the character occurs between `min` and `max` times.
+struct CountInRange;
+
+impl PasswordPolicy for CountInRange {
+    fn name(&self) -> String {
+        "count".into()
+    }
+
+    fn failures(&self, line:&PasswordLine) -> Result<Vec<PolicyFailure>,PolicyError> {
+        let letter_count = line.password.matches(line.character).count();
+        if letter_count >= line.min && letter_count <= line.max {
+            Ok(Vec::new())
+        } else {
+            self.fail(format!("{letter_count} times '{}', expected {} to {}",line.character,line.min,line.max))
         }
-        if value.matches(' ').count() != 2 {
-            return Err("format mismatch; exactly 2 spaces are expected.");
+    }
+}
+
+/// The Toboggan policy: the character is at exactly one of the (1-based) positions `min` and `max`.
+struct ExactlyOnePosition;
+
+impl PasswordPolicy for ExactlyOnePosition {
+    fn name(&self) -> String {
+        "position".into()
+    }
+
+    fn failures(&self, line:&PasswordLine) -> Result<Vec<PolicyFailure>,PolicyError> {
+        let characters:Vec<char> = line.password.chars().collect();
+        let at = |position:usize| {
+            if position == 0 || position > characters.len() {
+                Err(PolicyError {
+                    policy: self.name(),
+                    message: format!("position {position} is outside the {}-character password",characters.len()),
+                })
+            } else {
+                Ok(characters[position-1] == line.character)
+            }
+        };
+        match (at(line.min)?,at(line.max)?) {
+            (true,false)|(false,true) => Ok(Vec::new()),
+            (true,true) => self.fail(format!("'{}' at both positions {} and {}",line.character,line.min,line.max)),
+            (false,false) => self.fail(format!("'{}' at neither position {} nor {}",line.character,line.min,line.max)),
         }
-        if value.matches(':').count() != 1 {
-            return Err("format mismatch; exactly 1 : is expected.");
+    }
+}
+
+struct MatchesRegex(Regex);
+
+impl PasswordPolicy for MatchesRegex {
+    fn name(&self) -> String {
+        format!("regex:{}",self.0.as_str())
+    }
+
+    fn failures(&self, line:&PasswordLine) -> Result<Vec<PolicyFailure>,PolicyError> {
+        if self.0.is_match(&line.password) {
+            Ok(Vec::new())
+        } else {
+            self.fail("password does not match".into())
         }
-        let parts: Vec<&str> = value.split([' ',':','-'].as_ref()).collect();
-        let min:usize = parts[0].parse().unwrap();
-        let max:usize = parts[1].parse().unwrap();
-        let character = parts[2].chars().collect::<Vec<char>>()[0];
-        let password = parts[4].to_owned();
-        return Ok(PasswordLine { min, max: max, character: character, password: password });
     }
 }
 
-fn setup<'a>(input_path:&str) -> Vec<PasswordLine>{
-    let mut retval:Vec<PasswordLine> = Vec::<PasswordLine>::new();
-    let input_content = fs::read_to_string(input_path).unwrap();
-    for line in input_content.lines() {
-        if let Ok(pass_line) = PasswordLine::try_from(line) {
-            retval.push(pass_line);
+#[derive(Clone,Copy)]
+enum CharacterClass {
+    Lowercase,
+    Uppercase,
+    Digit,
+    Symbol,
+}
+
+impl CharacterClass {
+    fn from_name(name:&str) -> Result<Self,String> {
+        match name {
+            "lower" => Ok(CharacterClass::Lowercase),
+            "upper" => Ok(CharacterClass::Uppercase),
+            "digit" => Ok(CharacterClass::Digit),
+            "symbol" => Ok(CharacterClass::Symbol),
+            _ => Err(format!("Unknown character class <{name}>; expected lower, upper, digit or symbol.")),
+        }
+    }
+
+    fn contains(&self, c:char) -> bool {
+        match self {
+            CharacterClass::Lowercase => c.is_lowercase(),
+            CharacterClass::Uppercase => c.is_uppercase(),
+            CharacterClass::Digit => c.is_ascii_digit(),
+            CharacterClass::Symbol => !c.is_alphanumeric() && !c.is_whitespace(),
         }
     }
+}
+
+impl Display for CharacterClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}",
+        match self {
+            CharacterClass::Lowercase => "lower",
+            CharacterClass::Uppercase => "upper",
+            CharacterClass::Digit => "digit",
+            CharacterClass::Symbol => "symbol",
+        })
+    }
+}
+
+/// The password contains at least one character of each of the classes.
+struct RequiresClasses(Vec<CharacterClass>);
+
+impl PasswordPolicy for RequiresClasses {
+    fn name(&self) -> String {
+        let classes:Vec<String> = self.0.iter().map(|class| class.to_string()).collect();
+        format!("classes:{}",classes.join("+"))
+    }
+
+    fn failures(&self, line:&PasswordLine) -> Result<Vec<PolicyFailure>,PolicyError> {
+        Ok(self.0.iter()
+            .filter(|class| !line.password.chars().any(|c| class.contains(c)))
+            .map(|class| PolicyFailure { policy: self.name(), reason: format!("no {class} character") })
+            .collect())
+    }
+}
 
+/// Shannon entropy of the whole password, in bits, based on its own character frequencies.
+fn entropy(password:&str) -> f64 {
+    let characters:Vec<char> = password.chars().collect();
+    let length = characters.len() as f64;
+    let mut seen:Vec<char> = Vec::new();
+    let mut retval = 0.0;
+    for c in characters.iter() {
+        if seen.contains(c) {
+            continue;
+        }
+        seen.push(*c);
+        let p = characters.iter().filter(|other| *other == c).count() as f64 / length;
+        retval -= length * p * p.log2();
+    }
     retval
 }
 
-fn first_star(input:&Vec<PasswordLine>) -> String {
-    let mut correct_count:u32 = 0;
-    for pw in input{
-        let letter_count = pw.password.matches(pw.character).count();
-        if letter_count >= pw.min && letter_count <= pw.max {
-            correct_count += 1;
+/// The password carries at least this many bits of entropy.
+struct MinimumEntropy(f64);
+
+impl PasswordPolicy for MinimumEntropy {
+    fn name(&self) -> String {
+        format!("entropy:{}",self.0)
+    }
+
+    fn failures(&self, line:&PasswordLine) -> Result<Vec<PolicyFailure>,PolicyError> {
+        let bits = entropy(&line.password);
+        if bits >= self.0 {
+            Ok(Vec::new())
+        } else {
+            self.fail(format!("{bits:.2} bits of entropy, expected at least {}",self.0))
+        }
+    }
+}
+
+/// Passes when every one of the policies passes.
+struct AllOf(Vec<Box<dyn PasswordPolicy>>);
+
+impl PasswordPolicy for AllOf {
+    fn name(&self) -> String {
+        let names:Vec<String> = self.0.iter().map(|policy| policy.name()).collect();
+        format!("all({})",names.join(","))
+    }
+
+    fn failures(&self, line:&PasswordLine) -> Result<Vec<PolicyFailure>,PolicyError> {
+        let mut retval = Vec::new();
+        for policy in self.0.iter() {
+            retval.extend(policy.failures(line)?);
         }
+        Ok(retval)
     }
-    return correct_count.to_string();
+}
+
+/// Passes when at least one of the policies passes.
+struct AnyOf(Vec<Box<dyn PasswordPolicy>>);
+
+impl PasswordPolicy for AnyOf {
+    fn name(&self) -> String {
+        let names:Vec<String> = self.0.iter().map(|policy| policy.name()).collect();
+        format!("any({})",names.join(","))
+    }
+
+    fn failures(&self, line:&PasswordLine) -> Result<Vec<PolicyFailure>,PolicyError> {
+        //A branch that cannot judge the line does not stop another branch from passing it.
+        let mut retval = Vec::new();
+        let mut errors:Vec<PolicyError> = Vec::new();
+        for policy in self.0.iter() {
+            match policy.failures(line) {
+                Ok(failures) if failures.is_empty() => return Ok(Vec::new()),
+                Ok(failures) => retval.extend(failures),
+                Err(error) => errors.push(error),
+            }
+        }
+        match errors.len() {
+            0 => Ok(retval),
+            1 => Err(errors.remove(0)),
+            _ => Err(PolicyError {
+                policy: self.name(),
+                message: errors.iter().map(PolicyError::to_string).collect::<Vec<String>>().join("; "),
+            }),
+        }
+    }
+}
+
+/// Splits `a,b,c` on the commas that are not inside parentheses or single quotes.
+fn split_arguments(arguments:&str) -> Vec<&str> {
+    let mut retval = Vec::new();
+    let mut depth = 0;
+    let mut quoted = false;
+    let mut start = 0;
+    for (index,c) in arguments.char_indices() {
+        match c {
+            '\'' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => depth -= 1,
+            ',' if !quoted && depth == 0 => {
+                retval.push(&arguments[start..index]);
+                start = index + 1;
+            },
+            _ => (),
+        }
+    }
+    retval.push(&arguments[start..]);
+    retval
+}
+
+/// Builds a policy from a textual spec:
+/// `count`, `position`, `regex:<pattern>`, `classes:<class>+<class>...`, `entropy:<bits>`,
+/// or a combination `all(<spec>,<spec>...)`/`any(<spec>,<spec>...)`.
+/// A regex pattern containing commas or parentheses can be wrapped in single quotes.
+fn parse_policy(spec:&str) -> Result<Box<dyn PasswordPolicy>,String> {
+    let spec = spec.trim();
+    if let Some(inner) = spec.strip_prefix("all(").or(spec.strip_prefix("any(")) {
+        let inner = inner.strip_suffix(')').ok_or(format!("Missing ) in <{spec}>."))?;
+        let policies = split_arguments(inner)
+            .into_iter()
+            .map(parse_policy)
+            .collect::<Result<Vec<_>,_>>()?;
+        return if spec.starts_with("all(") {
+            Ok(Box::new(AllOf(policies)))
+        } else {
+            Ok(Box::new(AnyOf(policies)))
+        };
+    }
+    let (kind, argument) = spec.split_once(':').unwrap_or((spec,""));
+    match kind {
+        "count" => Ok(Box::new(CountInRange)),
+        "position" => Ok(Box::new(ExactlyOnePosition)),
+        "regex" => {
+            let pattern = argument.strip_prefix('\'').and_then(|p| p.strip_suffix('\'')).unwrap_or(argument);
+            Regex::new(pattern)
+                .map(|regex| Box::new(MatchesRegex(regex)) as Box<dyn PasswordPolicy>)
+                .map_err(|e| format!("Invalid regex <{pattern}>: {e}"))
+        },
+        "classes" => {
+            let classes = argument.split('+')
+                .map(CharacterClass::from_name)
+                .collect::<Result<Vec<_>,_>>()?;
+            Ok(Box::new(RequiresClasses(classes)))
+        },
+        "entropy" => argument.parse::<f64>()
+            .map(|bits| Box::new(MinimumEntropy(bits)) as Box<dyn PasswordPolicy>)
+            .map_err(|_| format!("Could not parse entropy <{argument}>.")),
+        _ => Err(format!("Unknown policy <{spec}>.")),
+    }
+}
+
+/// A line of the input that could not be parsed.
+struct BadLine {
+    line_number:usize,
+    error:ParseError,
+}
+
+impl Display for BadLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f,"Line {}, {}",self.line_number,self.error)
+    }
+}
+
+fn parse_input(input_content:&str) -> (Vec<PasswordLine>,Vec<BadLine>) {
+    let mut retval:Vec<PasswordLine> = Vec::<PasswordLine>::new();
+    let mut bad_lines:Vec<BadLine> = Vec::new();
+    for (index,line) in input_content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match PasswordLine::try_from(line) {
+            Ok(pass_line) => retval.push(pass_line),
+            Err(error) => bad_lines.push(BadLine { line_number: index+1, error }),
+        }
+    }
+
+    (retval,bad_lines)
+}
+
+fn setup(input_path:&str) -> (Vec<PasswordLine>,Vec<BadLine>) {
+    let input_content = fs::read_to_string(input_path).expect("Could not read file.");
+    parse_input(&input_content)
+}
+
+enum Verdict {
+    Pass,
+    Fail(Vec<PolicyFailure>),
+    Error(PolicyError),
+}
+
+impl Verdict {
+    fn label(&self) -> &'static str {
+        match self {
+            Verdict::Pass => "pass",
+            Verdict::Fail(_) => "fail",
+            Verdict::Error(_) => "error",
+        }
+    }
+
+    fn details(&self) -> Vec<String> {
+        match self {
+            Verdict::Pass => Vec::new(),
+            Verdict::Fail(failures) => failures.iter().map(|failure| failure.to_string()).collect(),
+            Verdict::Error(error) => vec![error.to_string()],
+        }
+    }
+}
+
+/// One line of the input, as it appears in an audit report.
+struct AuditEntry {
+    line_number:usize,
+    text:String,
+    line:Result<PasswordLine,ParseError>,
+    /// One verdict per policy of the report; empty if the line could not be parsed.
+    verdicts:Vec<Verdict>,
+}
+
+impl AuditEntry {
+    fn character_count(&self) -> Option<usize> {
+        self.line.as_ref().ok().map(|pw| pw.password.matches(pw.character).count())
+    }
+
+    fn length(&self) -> Option<usize> {
+        self.line.as_ref().ok().map(|pw| pw.password.chars().count())
+    }
+
+    fn notes(&self) -> String {
+        match &self.line {
+            Ok(_) => self.verdicts.iter().flat_map(Verdict::details).collect::<Vec<String>>().join("; "),
+            Err(error) => error.to_string(),
+        }
+    }
+}
+
+/// Every line of an input checked against a set of policies, plus summary statistics.
+struct AuditReport {
+    policies:Vec<String>,
+    entries:Vec<AuditEntry>,
+}
+
+impl AuditReport {
+    fn build(input_content:&str, policies:&[Box<dyn PasswordPolicy>]) -> Self {
+        let mut entries = Vec::new();
+        for (index,text) in input_content.lines().enumerate() {
+            if text.trim().is_empty() {
+                continue;
+            }
+            let line = PasswordLine::try_from(text);
+            let verdicts = match &line {
+                Ok(pw) => policies.iter()
+                    .map(|policy| match policy.failures(pw) {
+                        Ok(failures) if failures.is_empty() => Verdict::Pass,
+                        Ok(failures) => Verdict::Fail(failures),
+                        Err(error) => Verdict::Error(error),
+                    })
+                    .collect(),
+                Err(_) => Vec::new(),
+            };
+            entries.push(AuditEntry { line_number: index+1, text: text.to_string(), line, verdicts });
+        }
+        AuditReport { policies: policies.iter().map(|policy| policy.name()).collect(), entries }
+    }
+
+    /// (passed, failed, errors) for the policy at this index.
+    fn policy_totals(&self, policy:usize) -> (usize,usize,usize) {
+        let mut retval = (0,0,0);
+        for verdict in self.entries.iter().filter_map(|entry| entry.verdicts.get(policy)) {
+            match verdict {
+                Verdict::Pass => retval.0 += 1,
+                Verdict::Fail(_) => retval.1 += 1,
+                Verdict::Error(_) => retval.2 += 1,
+            }
+        }
+        retval
+    }
+
+    /// The policy character that shows up most on lines failing at least one policy, and how often.
+    fn most_common_failing_character(&self) -> Option<(char,usize)> {
+        let mut counts:HashMap<char,usize> = HashMap::new();
+        for entry in self.entries.iter() {
+            if let Ok(pw) = &entry.line {
+                if entry.verdicts.iter().any(|verdict| matches!(verdict, Verdict::Fail(_))) {
+                    *counts.entry(pw.character).or_insert(0) += 1;
+                }
+            }
+        }
+        //Break ties on the character, so the report doesn't depend on hashing order.
+        counts.into_iter().max_by(|a,b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)))
+    }
+
+    /// Password length, mapped to the number of passwords of that length.
+    fn length_distribution(&self) -> BTreeMap<usize,usize> {
+        let mut retval = BTreeMap::new();
+        for length in self.entries.iter().filter_map(AuditEntry::length) {
+            *retval.entry(length).or_insert(0) += 1;
+        }
+        retval
+    }
+
+    fn unparsed(&self) -> usize {
+        self.entries.iter().filter(|entry| entry.line.is_err()).count()
+    }
+
+    /// Writes two tables, separated by an empty line: one row per input line, then the summary.
+    fn write_csv(&self, out:&mut impl Write) -> io::Result<()> {
+        let mut header = vec!["line","min","max","character","password","length","character_count"];
+        header.extend(self.policies.iter().map(String::as_str));
+        header.push("notes");
+        writeln!(out,"{}",header.iter().map(|field| csv_field(field)).collect::<Vec<String>>().join(","))?;
+
+        for entry in self.entries.iter() {
+            let mut row:Vec<String> = vec![entry.line_number.to_string()];
+            match &entry.line {
+                Ok(pw) => {
+                    row.push(pw.min.to_string());
+                    row.push(pw.max.to_string());
+                    row.push(pw.character.to_string());
+                    row.push(pw.password.clone());
+                },
+                Err(_) => {
+                    row.extend(["","",""].map(String::from));
+                    row.push(entry.text.clone());
+                },
+            }
+            row.push(entry.length().map(|length| length.to_string()).unwrap_or_default());
+            row.push(entry.character_count().map(|count| count.to_string()).unwrap_or_default());
+            for policy in 0..self.policies.len() {
+                row.push(entry.verdicts.get(policy).map(|verdict| verdict.label()).unwrap_or("unparsed").to_string());
+            }
+            row.push(entry.notes());
+            writeln!(out,"{}",row.iter().map(|field| csv_field(field)).collect::<Vec<String>>().join(","))?;
+        }
+
+        writeln!(out)?;
+        writeln!(out,"statistic,key,value")?;
+        writeln!(out,"lines,,{}",self.entries.len())?;
+        writeln!(out,"unparsed,,{}",self.unparsed())?;
+        for (index,name) in self.policies.iter().enumerate() {
+            let (passed,failed,errors) = self.policy_totals(index);
+            let name = csv_field(name);
+            writeln!(out,"passed,{name},{passed}")?;
+            writeln!(out,"failed,{name},{failed}")?;
+            writeln!(out,"errors,{name},{errors}")?;
+        }
+        if let Some((character,count)) = self.most_common_failing_character() {
+            writeln!(out,"most_common_failing_character,{},{count}",csv_field(&character.to_string()))?;
+        }
+        for (length,count) in self.length_distribution() {
+            writeln!(out,"password_length,{length},{count}")?;
+        }
+        Ok(())
+    }
+
+    fn write_json(&self, out:&mut impl Write) -> io::Result<()> {
+        let policies:Vec<String> = self.policies.iter().map(|name| json_string(name)).collect();
+        writeln!(out,"{{")?;
+        writeln!(out,"  \"policies\": [{}],",policies.join(", "))?;
+        writeln!(out,"  \"lines\": [")?;
+        for (index,entry) in self.entries.iter().enumerate() {
+            let mut fields = vec![format!("\"line\": {}",entry.line_number)];
+            match &entry.line {
+                Ok(pw) => {
+                    fields.push(format!("\"min\": {}",pw.min));
+                    fields.push(format!("\"max\": {}",pw.max));
+                    fields.push(format!("\"character\": {}",json_string(&pw.character.to_string())));
+                    fields.push(format!("\"password\": {}",json_string(&pw.password)));
+                    fields.push(format!("\"length\": {}",entry.length().unwrap_or_default()));
+                    fields.push(format!("\"character_count\": {}",entry.character_count().unwrap_or_default()));
+                    let verdicts:Vec<String> = self.policies.iter()
+                        .zip(entry.verdicts.iter())
+                        .map(|(name,verdict)| {
+                            let details:Vec<String> = verdict.details().iter().map(|detail| json_string(detail)).collect();
+                            format!("{{\"policy\": {}, \"result\": \"{}\", \"details\": [{}]}}",json_string(name),verdict.label(),details.join(", "))
+                        })
+                        .collect();
+                    fields.push(format!("\"policies\": [{}]",verdicts.join(", ")));
+                },
+                Err(error) => {
+                    fields.push(format!("\"text\": {}",json_string(&entry.text)));
+                    fields.push(format!("\"parse_error\": {{\"column\": {}, \"message\": {}}}",error.column,json_string(&error.message)));
+                },
+            }
+            let separator = if index + 1 < self.entries.len() { "," } else { "" };
+            writeln!(out,"    {{{}}}{separator}",fields.join(", "))?;
+        }
+        writeln!(out,"  ],")?;
+
+        let totals:Vec<String> = self.policies.iter()
+            .enumerate()
+            .map(|(index,name)| {
+                let (passed,failed,errors) = self.policy_totals(index);
+                format!("{}: {{\"passed\": {passed}, \"failed\": {failed}, \"errors\": {errors}}}",json_string(name))
+            })
+            .collect();
+        let most_common = match self.most_common_failing_character() {
+            Some((character,count)) => format!("{{\"character\": {}, \"count\": {count}}}",json_string(&character.to_string())),
+            None => "null".into(),
+        };
+        let lengths:Vec<String> = self.length_distribution()
+            .iter()
+            .map(|(length,count)| format!("\"{length}\": {count}"))
+            .collect();
+        writeln!(out,"  \"summary\": {{")?;
+        writeln!(out,"    \"lines\": {},",self.entries.len())?;
+        writeln!(out,"    \"unparsed\": {},",self.unparsed())?;
+        writeln!(out,"    \"policies\": {{{}}},",totals.join(", "))?;
+        writeln!(out,"    \"most_common_failing_character\": {most_common},")?;
+        writeln!(out,"    \"password_lengths\": {{{}}}",lengths.join(", "))?;
+        writeln!(out,"  }}")?;
+        writeln!(out,"}}")
+    }
+}
+
+/// Quotes a CSV field if it contains anything that would otherwise break the row.
+fn csv_field(field:&str) -> String {
+    if field.contains([',','"','\n','\r']) {
+        format!("\"{}\"",field.replace('"',"\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn json_string(value:&str) -> String {
+    let mut retval = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => retval.push_str("\\\""),
+            '\\' => retval.push_str("\\\\"),
+            '\n' => retval.push_str("\\n"),
+            '\r' => retval.push_str("\\r"),
+            '\t' => retval.push_str("\\t"),
+            c if c.is_control() => retval.push_str(&format!("\\u{:04x}",c as u32)),
+            c => retval.push(c),
+        }
+    }
+    retval.push('"');
+    retval
+}
+
+/// Lines for which the policy could not be checked count as not passing.
+fn count_passing(input:&[PasswordLine], policy:&dyn PasswordPolicy) -> usize {
+    input.iter().filter(|pw| policy.passes(pw).unwrap_or(false)).count()
+}
+
+fn first_star(input:&Vec<PasswordLine>) -> String {
+    count_passing(input, &CountInRange).to_string()
 }
 
 fn second_star(input:&Vec<PasswordLine>) -> String {
-    let mut correct_count:u32 = 0;
+    count_passing(input, &ExactlyOnePosition).to_string()
+}
 
-    for pw in input {
-        let characters:Vec<char> = pw.password.chars().collect();
-        let mut matches = 0;
-        if characters[pw.min-1] == pw.character
-            { matches += 1; }
-        if characters[pw.max-1] == pw.character
-            { matches += 1; }
-        if matches == 1 
-            { correct_count += 1;}
+/// `day2-policy <input> <policy>`: checks every line against a policy, and reports why
+/// failing lines fail.
+pub fn policy_command(args:&[String]) -> Result<(),String> {
+    let [input_path, spec] = args else {
+        return Err("Usage: day2-policy <input> <policy>".into());
+    };
+    let policy = parse_policy(spec)?;
+    let input_content = fs::read_to_string(input_path).map_err(|e| format!("Could not read {input_path}: {e}"))?;
+    let (data,bad_lines) = parse_input(&input_content);
+    for bad in bad_lines.iter() {
+        println!("{bad}");
     }
+    for pw in data.iter() {
+        match policy.failures(pw) {
+            Ok(failures) if failures.is_empty() => println!("{pw}: pass"),
+            Ok(failures) => {
+                let reasons:Vec<String> = failures.iter().map(|failure| failure.to_string()).collect();
+                println!("{pw}: FAIL ({})",reasons.join("; "));
+            },
+            Err(error) => println!("{pw}: ERROR ({error})"),
+        }
+    }
+    println!("{} of {} lines pass {}.",count_passing(&data, policy.as_ref()),data.len(),policy.name());
+    Ok(())
+}
 
-    return correct_count.to_string();
+/// `day2-report <input> csv|json [policy...]`: writes an audit of every line to stdout.
+/// Without policies, the report covers the `count` and `position` policies of both stars.
+pub fn report_command(args:&[String]) -> Result<(),String> {
+    let usage = "Usage: day2-report <input> csv|json [policy...]";
+    let [input_path, format, specs @ ..] = args else {
+        return Err(usage.into());
+    };
+    let policies:Vec<Box<dyn PasswordPolicy>> = if specs.is_empty() {
+        vec![Box::new(CountInRange), Box::new(ExactlyOnePosition)]
+    } else {
+        specs.iter().map(|spec| parse_policy(spec)).collect::<Result<_,_>>()?
+    };
+    let input_content = fs::read_to_string(input_path).map_err(|e| format!("Could not read {input_path}: {e}"))?;
+    let report = AuditReport::build(&input_content, &policies);
+    let mut out = io::stdout().lock();
+    match format.as_str() {
+        "csv" => report.write_csv(&mut out),
+        "json" => report.write_json(&mut out),
+        _ => return Err(format!("Unknown format <{format}>. {usage}")),
+    }.map_err(|e| format!("Could not write report: {e}"))
 }
 
 pub fn run_day(input_path:&str) {
-    let data = setup(input_path);
+    let (data,bad_lines) = setup(input_path);
+    for bad in bad_lines.iter() {
+        eprintln!("{bad}");
+    }
     let first = first_star(&data);
     let second = second_star(&data);
 
     println!("Day 2.\nStar one: {first}\nStar two: {second}");
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line:&str) -> PasswordLine {
+        PasswordLine::try_from(line).unwrap_or_else(|e| panic!("<{line}> did not parse: {e}"))
+    }
+
+    fn error_column(line:&str) -> Option<usize> {
+        PasswordLine::try_from(line).err().map(|e| e.column)
+    }
+
+    #[test]
+    fn reads_the_puzzle_lines() {
+        let lines = ["1-3 a: abcde", "1-3 b: cdefg", "2-9 c: ccccccccc"].map(parse);
+        assert_eq!((lines[0].min, lines[0].max, lines[0].character, lines[0].password.as_str()), (1, 3, 'a', "abcde"));
+        assert_eq!((lines[2].min, lines[2].max, lines[2].character, lines[2].password.as_str()), (2, 9, 'c', "ccccccccc"));
+        for line in lines.iter() {
+            assert_eq!(PasswordLine::try_from(line.to_string().as_str()).map(|again| again.to_string()).ok(), Some(line.to_string()));
+        }
+
+        let verdicts = |policy:&dyn PasswordPolicy| lines.iter().map(|line| policy.passes(line).ok()).collect::<Vec<_>>();
+        assert_eq!(verdicts(&CountInRange), vec![Some(true), Some(false), Some(true)]);
+        assert_eq!(verdicts(&ExactlyOnePosition), vec![Some(true), Some(false), Some(false)]);
+    }
+
+    #[test]
+    fn errors_point_at_the_column() {
+        assert_eq!(error_column("x-3 a: abcde"), Some(1));
+        assert_eq!(error_column("1-3 a:abcde"), Some(7));
+        assert_eq!(error_column("1-3 : abcde"), Some(5));
+        //Trailing whitespace is trimmed, so this is missing the space before the password.
+        assert_eq!(error_column("1-3 a: "), Some(7));
+        assert_eq!(error_column(""), Some(1));
+    }
+
+    #[test]
+    fn overflowing_bounds_are_errors() {
+        let error = PasswordLine::try_from("99999999999999999999999-3 a: abcde").err().expect("Bound should not fit.");
+        assert_eq!(error.column, 1);
+        assert!(error.message.contains("too large"), "{}", error.message);
+        assert_eq!(error_column("1-99999999999999999999999 a: abcde"), Some(3));
+    }
+
+    #[test]
+    fn whitespace() {
+        assert_eq!(parse("1-3 a: abcde  ").password, "abcde");
+        assert_eq!(parse("1-3 a: abcde\r").password, "abcde");
+        assert_eq!(error_column(" 1-3 a: abcde"), Some(1));
+        assert_eq!(error_column("1-3 a: ab cde"), Some(10));
+        assert_eq!(error_column("1-3  a: abcde"), Some(5));
+    }
+
+    #[test]
+    fn positions_outside_the_password_are_policy_errors() {
+        for line in ["0-2 a: abcde", "1-6 a: abcde", "9-12 a: ab"] {
+            let error = ExactlyOnePosition.failures(&parse(line)).err().unwrap_or_else(|| panic!("<{line}> should not be checkable."));
+            assert_eq!(error.policy, "position");
+            assert!(error.message.contains("outside"), "{}", error.message);
+        }
+        assert_eq!(ExactlyOnePosition.passes(&parse("1-5 a: abcde")).ok(), Some(true));
+    }
+}
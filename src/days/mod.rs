@@ -18,6 +18,9 @@ pub mod day_seventeen;
 pub mod day_eighteen;
 pub mod day_nineteen;
 pub mod day_twenty;
+pub mod day_twenty_three;
+pub mod day_twenty_four;
+pub mod day_twenty_five;
 
 pub fn verbosity_set(verbose:bool) {
     day_five::verbosity_set(verbose);
@@ -1,6 +1,29 @@
+mod common;
 mod days;
 
-fn main() {
+use std::env;
+
+const USAGE:&str = "Usage: aoc2020 [command] [arguments]
+Without a command, every day is run on its input from the inputs folder.
+Commands:
+    day1-ksum <input> <k> <target>
+    day1-stream <input> [target]
+    day2-policy <input> <policy>
+    day2-report <input> csv|json [policy...]
+    day3-slopes <input> [--wrap] <right,down>...
+    day3-rank <input> [--wrap] <max_right> <max_down> [count]
+    day3-render <input> <right,down> [ppm|svg <output>]
+    day4-validate <input> [schema]
+    day5-pass [--layout <row_bits>,<col_bits>] <code|row,col>...
+    day5-seats <input> [--layout <row_bits>,<col_bits>]
+    day6-answers <input> <k>
+    day6-stats <input> [table|csv]
+    day7-bags <input> ancestors|descendants|parents|children|rule|total|chain|check [colour] [colour]
+    day7-dot <input> [ancestors|descendants <colour>] [--highlight one|two]
+    day8-debug <input>
+    day8-repair <input>";
+
+fn run_all() {
     println!("Hello, world! AoC 2020!");
     days::verbosity_set(false);
     days::day_one::run_day("inputs/day_1.txt");
@@ -23,4 +46,38 @@ fn main() {
     days::day_eighteen::run_day("inputs/day_18.txt"); 
     days::day_nineteen::run_day("inputs/day_19.txt");
     days::day_twenty::run_day("inputs/day_20.txt");
+    days::day_twenty_three::run_day("inputs/day_23.txt");
+    days::day_twenty_four::run_day("inputs/day_24.txt");
+    days::day_twenty_five::run_day("inputs/day_25.txt");
+}
+
+fn main() {
+    let args:Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        None => {
+            run_all();
+            Ok(())
+        },
+        Some("day1-ksum") => days::day_one::k_sum_command(&args[1..]),
+        Some("day1-stream") => days::day_one::stream_command(&args[1..]),
+        Some("day2-policy") => days::day_two::policy_command(&args[1..]),
+        Some("day2-report") => days::day_two::report_command(&args[1..]),
+        Some("day3-slopes") => days::day_three::slopes_command(&args[1..]),
+        Some("day3-rank") => days::day_three::rank_command(&args[1..]),
+        Some("day3-render") => days::day_three::render_command(&args[1..]),
+        Some("day4-validate") => days::day_four::validate_command(&args[1..]),
+        Some("day5-pass") => days::day_five::pass_command(&args[1..]),
+        Some("day5-seats") => days::day_five::seats_command(&args[1..]),
+        Some("day6-answers") => days::day_six::answers_command(&args[1..]),
+        Some("day6-stats") => days::day_six::stats_command(&args[1..]),
+        Some("day7-bags") => days::day_seven::bags_command(&args[1..]),
+        Some("day7-dot") => days::day_seven::dot_command(&args[1..]),
+        Some("day8-debug") => days::day_eight::debug_command(&args[1..]),
+        Some("day8-repair") => days::day_eight::repair_command(&args[1..]),
+        Some(other) => Err(format!("Unknown command <{other}>.\n{USAGE}")),
+    };
+    if let Err(message) = result {
+        eprintln!("{message}");
+        std::process::exit(1);
+    }
 }